@@ -0,0 +1,374 @@
+use std::io;
+use std::collections::btree_map::BTreeMap as Map;
+
+use super::nbt::Tag;
+use crate::model::block::HeightRange;
+
+
+const DATA_VERSION_NO_SPANNING: i32 = 2529;   // 20w17a: packed entries no longer cross long boundaries
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn ceil_log2(n: usize) -> usize {
+    if n <= 1 {
+        0
+    } else {
+        (usize::MAX.count_ones() - (n - 1).leading_zeros()) as usize
+    }
+}
+
+
+/**
+ *  `Name` + `Properties` of a palette entry; `state` is kept as sorted `key=value` strings
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockStateEntry {
+
+    pub name: String,
+
+    pub state: Vec<String>,
+
+}
+
+impl BlockStateEntry {
+
+    fn from_nbt(tag: &Tag) -> io::Result<Self> {
+        let name = tag.get("Name").and_then(Tag::as_str).ok_or_else(|| invalid("palette: Name"))?.to_string();
+        let mut state = Vec::new();
+        if let Some(props) = tag.get("Properties").and_then(Tag::as_compound) {
+            for (k, v) in props {
+                if let Some(v) = v.as_str() {
+                    state.push(format!("{}={}", k, v));
+                }
+            }
+        }
+        Ok(BlockStateEntry {
+            name,
+            state
+        })
+    }
+
+    pub fn state_iter(&self) -> impl Iterator<Item = &str> {
+        self.state.iter().map(String::as_str)
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        for s in &self.state {
+            let mut it = s.splitn(2, '=');
+            if it.next() == Some(key) {
+                return it.next();
+            }
+        }
+        None
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BiomeRef<'a> {
+    Id(i32),            // before 1.18
+    Name(&'a str),      // 1.18 and later
+}
+
+
+struct PackedArray {
+    data: Vec<i64>,
+    bits: usize,
+    spanning: bool,
+}
+
+impl PackedArray {
+
+    fn new(data: &[i64], bits: usize, spanning: bool) -> Self {
+        PackedArray {
+            data: data.to_vec(),
+            bits,
+            spanning
+        }
+    }
+
+    fn get(&self, index: usize) -> usize {
+        let bits = self.bits;
+        if bits == 0 {
+            return 0;
+        }
+        let mask = (1u64 << bits) - 1;
+        if self.spanning {
+            let offset = index * bits;
+            let i = offset / 64;
+            let shift = offset % 64;
+            let lo = *self.data.get(i).unwrap_or(&0) as u64 >> shift;
+            let v = if shift + bits > 64 {
+                let hi = *self.data.get(i + 1).unwrap_or(&0) as u64;
+                lo | (hi << (64 - shift))
+            } else {
+                lo
+            };
+            (v & mask) as usize
+        } else {
+            let per_long = 64 / bits;
+            let i = index / per_long;
+            let shift = (index % per_long) * bits;
+            let v = *self.data.get(i).unwrap_or(&0) as u64 >> shift;
+            (v & mask) as usize
+        }
+    }
+}
+
+
+struct Section {
+    palette: Vec<usize>,
+    blocks: Option<PackedArray>,
+    biome_palette: Vec<usize>,
+    biomes: Option<PackedArray>,
+    block_light: Option<Vec<i8>>,
+    sky_light: Option<Vec<i8>>,
+}
+
+impl Section {
+
+    fn nibble(array: &Option<Vec<i8>>, index: usize) -> Option<u8> {
+        let b = *array.as_ref()?.get(index / 2)? as u8;
+        Some(if index & 1 == 0 { b & 0x0F } else { b >> 4 })
+    }
+}
+
+
+enum Biomes {
+    None,
+    Columns(Vec<i32>),      // 16x16, before 1.15
+    Cells(Vec<i32>),        // 4x4x4 cells over the whole height, 1.15 - 1.17
+    Sections,               // per section palette, 1.18 and later
+}
+
+
+/**
+ *  one 16xNx16 chunk decoded from region nbt; coordinates are chunk local, `y` is absolute
+ */
+pub struct Chunk {
+
+    pub x: i32,
+
+    pub z: i32,
+
+    pub data_version: i32,
+
+    pub status: String,
+
+    height: HeightRange,
+
+    palette: Vec<BlockStateEntry>,
+
+    biome_palette: Vec<String>,
+
+    sections: Map<i32, Section>,
+
+    biomes: Biomes,
+
+    heightmaps: Map<String, PackedArray>,
+
+    numeric_blocks: bool,
+
+}
+
+impl Chunk {
+
+    pub fn from_nbt(root: &Tag) -> io::Result<Self> {
+        let data_version = root.get("DataVersion").and_then(Tag::as_i32).unwrap_or(0);
+        let spanning = data_version < DATA_VERSION_NO_SPANNING;
+        let (level, height) = match root.get("Level") {
+            Some(level) => (level, HeightRange::LEGACY),
+            None => {
+                let min = root.get("yPos").and_then(Tag::as_i32).map(|y| y * 16).unwrap_or(HeightRange::EXTENDED.min);
+                (root, HeightRange::new(min, HeightRange::EXTENDED.max))
+            }
+        };
+        let x = level.get("xPos").and_then(Tag::as_i32).ok_or_else(|| invalid("chunk: xPos"))?;
+        let z = level.get("zPos").and_then(Tag::as_i32).ok_or_else(|| invalid("chunk: zPos"))?;
+        let status = level.get("Status").and_then(Tag::as_str).unwrap_or("").to_string();
+
+        let mut palette: Vec<BlockStateEntry> = Vec::new();
+        let mut biome_palette: Vec<String> = Vec::new();
+        let mut sections = Map::new();
+        let mut numeric_blocks = false;
+        let list = level.get("sections").or_else(|| level.get("Sections")).and_then(Tag::as_list).unwrap_or(&[]);
+        for s in list {
+            let y = match s.get("Y").and_then(Tag::as_i32) {
+                Some(y) => y,
+                None => continue,
+            };
+            numeric_blocks |= s.get("Blocks").is_some() && s.get("Palette").is_none() && s.get("block_states").is_none();
+            let (pal, data) = match s.get("block_states") {
+                Some(states) => (states.get("palette"), states.get("data")),
+                None => (s.get("Palette"), s.get("BlockStates")),
+            };
+            let mut section_palette = Vec::new();
+            for entry in pal.and_then(Tag::as_list).unwrap_or(&[]) {
+                let entry = BlockStateEntry::from_nbt(entry)?;
+                let i = match palette.iter().position(|e| e == &entry) {
+                    Some(i) => i,
+                    None => {
+                        palette.push(entry);
+                        palette.len() - 1
+                    }
+                };
+                section_palette.push(i);
+            }
+            let blocks = data.and_then(Tag::as_long_array).map(|data| {
+                let bits = std::cmp::max(4, ceil_log2(section_palette.len()));
+                PackedArray::new(data, bits, spanning)
+            });
+            let mut section_biome_palette = Vec::new();
+            let mut section_biomes = None;
+            if let Some(biomes) = s.get("biomes") {
+                for name in biomes.get("palette").and_then(Tag::as_list).unwrap_or(&[]) {
+                    let biome = name.as_str().unwrap_or("").to_string();
+                    let i = match biome_palette.iter().position(|e| e == &biome) {
+                        Some(i) => i,
+                        None => {
+                            biome_palette.push(biome);
+                            biome_palette.len() - 1
+                        }
+                    };
+                    section_biome_palette.push(i);
+                }
+                section_biomes = biomes.get("data").and_then(Tag::as_long_array).map(|data| {
+                    PackedArray::new(data, ceil_log2(section_biome_palette.len()), false)
+                });
+            }
+            sections.insert(y, Section {
+                palette: section_palette,
+                blocks,
+                biome_palette: section_biome_palette,
+                biomes: section_biomes,
+                block_light: s.get("BlockLight").and_then(Tag::as_byte_array).map(|v| v.to_vec()),
+                sky_light: s.get("SkyLight").and_then(Tag::as_byte_array).map(|v| v.to_vec()),
+            });
+        }
+
+        let biomes = match level.get("Biomes").and_then(Tag::as_int_array) {
+            Some(list) if list.len() == 256 => Biomes::Columns(list.to_vec()),
+            Some(list) if list.len() == 1024 => Biomes::Cells(list.to_vec()),
+            _ if !biome_palette.is_empty() => Biomes::Sections,
+            _ => Biomes::None,
+        };
+
+        let mut heightmaps = Map::new();
+        if let Some(maps) = level.get("Heightmaps").and_then(Tag::as_compound) {
+            let bits = ceil_log2(height.span() as usize + 1);
+            for (k, v) in maps {
+                if let Some(data) = v.as_long_array() {
+                    heightmaps.insert(k.clone(), PackedArray::new(data, bits, spanning));
+                }
+            }
+        }
+
+        Ok(Chunk {
+            x,
+            z,
+            data_version,
+            status,
+            height,
+            palette,
+            biome_palette,
+            sections,
+            biomes,
+            heightmaps,
+            numeric_blocks,
+        })
+    }
+
+    /**
+     *  whether the sections store numeric `Blocks`/`Data` ids, as before 1.13; those read as air
+     */
+    pub fn is_pre_flattening(&self) -> bool {
+        self.numeric_blocks
+    }
+
+    /**
+     *  every numeric biome id of chunks before 1.18, nothing for named biomes
+     */
+    pub fn biome_ids(&self) -> &[i32] {
+        match &self.biomes {
+            Biomes::Columns(list) | Biomes::Cells(list) => list.as_slice(),
+            _ => &[],
+        }
+    }
+
+    pub fn height_range(&self) -> HeightRange {
+        self.height
+    }
+
+    /**
+     *  all block states referenced by this chunk; `block` returns indices into it
+     */
+    pub fn palette(&self) -> &[BlockStateEntry] {
+        self.palette.as_slice()
+    }
+
+    /**
+     *  section indices present in the chunk, lowest first; section `y` holds blocks `y * 16 .. y * 16 + 16`
+     */
+    pub fn sections(&self) -> impl Iterator<Item = i32> + '_ {
+        self.sections.keys().cloned()
+    }
+
+    /**
+     *  `None` if the section is missing, which means air
+     */
+    pub fn block(&self, x: usize, y: i32, z: usize) -> Option<usize> {
+        let section = self.sections.get(&y.div_euclid(16))?;
+        let index = (y.rem_euclid(16) as usize * 16 + z) * 16 + x;
+        let i = match &section.blocks {
+            Some(blocks) => blocks.get(index),
+            None => 0,
+        };
+        section.palette.get(i).cloned()
+    }
+
+    pub fn biome(&self, x: usize, y: i32, z: usize) -> Option<BiomeRef<'_>> {
+        match &self.biomes {
+            Biomes::None => None,
+            Biomes::Columns(list) => list.get(z * 16 + x).map(|id| BiomeRef::Id(*id)),
+            Biomes::Cells(list) => {
+                let cy = ((y - self.height.min) >> 2).clamp(0, 63) as usize;
+                list.get((cy * 4 + (z >> 2)) * 4 + (x >> 2)).map(|id| BiomeRef::Id(*id))
+            },
+            Biomes::Sections => {
+                let section = self.sections.get(&y.div_euclid(16))?;
+                let index = ((y.rem_euclid(16) as usize >> 2) * 4 + (z >> 2)) * 4 + (x >> 2);
+                let i = match &section.biomes {
+                    Some(biomes) => biomes.get(index),
+                    None => 0,
+                };
+                let i = *section.biome_palette.get(i)?;
+                self.biome_palette.get(i).map(|name| BiomeRef::Name(name.as_str()))
+            }
+        }
+    }
+
+    /**
+     *  `block_light + sky_light * 16`, same packing as the voxelmap cache
+     */
+    pub fn light(&self, x: usize, y: i32, z: usize) -> u8 {
+        let index = (y.rem_euclid(16) as usize * 16 + z) * 16 + x;
+        match self.sections.get(&y.div_euclid(16)) {
+            Some(section) => {
+                let block = Section::nibble(&section.block_light, index).unwrap_or(0);
+                let sky = Section::nibble(&section.sky_light, index).unwrap_or(15);
+                block + sky * 16
+            },
+            None => 15 * 16,
+        }
+    }
+
+    /**
+     *  first free `y` above the column from heightmap `kind` (`WORLD_SURFACE`, `MOTION_BLOCKING`, `OCEAN_FLOOR`, ...)
+     */
+    pub fn height(&self, kind: &str, x: usize, z: usize) -> Option<i32> {
+        let map = self.heightmaps.get(kind)?;
+        Some(map.get(z * 16 + x) as i32 + self.height.min)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod nbt;
+pub mod region;
+pub mod chunk;
@@ -0,0 +1,197 @@
+use std::io;
+use std::io::Read;
+use std::collections::btree_map::BTreeMap as Map;
+
+
+/**
+ *  named binary tag, big-endian as stored in region files
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Map<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Self::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Byte(v) => Some(*v as i64),
+            Self::Short(v) => Some(*v as i64),
+            Self::Int(v) => Some(*v as i64),
+            Self::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().map(|v| v as i32)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Self::List(list) => Some(list.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&Map<String, Tag>> {
+        match self {
+            Self::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Self::ByteArray(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            Self::IntArray(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Self::LongArray(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+
+/**
+ *  read the root tag; returns its name and value
+ */
+pub fn read<R: Read>(reader: &mut R) -> io::Result<(String, Tag)> {
+    let id = read_u8(reader)?;
+    if id == 0 {
+        return Ok((String::new(), Tag::End));
+    }
+    let name = read_string(reader)?;
+    let tag = read_payload(reader, id, 0)?;
+    Ok((name, tag))
+}
+
+const MAX_DEPTH: usize = 512;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_payload<R: Read>(reader: &mut R, id: u8, depth: usize) -> io::Result<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(invalid(String::from("nbt: nested too deep")));
+    }
+    let tag = match id {
+        0 => Tag::End,
+        1 => Tag::Byte(read_u8(reader)? as i8),
+        2 => Tag::Short(i16::from_be_bytes(read_array(reader)?)),
+        3 => Tag::Int(i32::from_be_bytes(read_array(reader)?)),
+        4 => Tag::Long(i64::from_be_bytes(read_array(reader)?)),
+        5 => Tag::Float(f32::from_be_bytes(read_array(reader)?)),
+        6 => Tag::Double(f64::from_be_bytes(read_array(reader)?)),
+        7 => {
+            let len = read_len(reader)?;
+            let mut buf = vec![0u8; len];
+            reader.read_exact(&mut buf)?;
+            Tag::ByteArray(buf.into_iter().map(|b| b as i8).collect())
+        },
+        8 => Tag::String(read_string(reader)?),
+        9 => {
+            let elem = read_u8(reader)?;
+            let len = read_len(reader)?;
+            let mut list = Vec::with_capacity(len.min(4096));
+            for _ in 0..len {
+                list.push(read_payload(reader, elem, depth + 1)?);
+            }
+            Tag::List(list)
+        },
+        10 => {
+            let mut map = Map::new();
+            loop {
+                let id = read_u8(reader)?;
+                if id == 0 {
+                    break;
+                }
+                let name = read_string(reader)?;
+                map.insert(name, read_payload(reader, id, depth + 1)?);
+            }
+            Tag::Compound(map)
+        },
+        11 => {
+            let len = read_len(reader)?;
+            let mut list = Vec::with_capacity(len.min(4096));
+            for _ in 0..len {
+                list.push(i32::from_be_bytes(read_array(reader)?));
+            }
+            Tag::IntArray(list)
+        },
+        12 => {
+            let len = read_len(reader)?;
+            let mut list = Vec::with_capacity(len.min(4096));
+            for _ in 0..len {
+                list.push(i64::from_be_bytes(read_array(reader)?));
+            }
+            Tag::LongArray(list)
+        },
+        _ => return Err(invalid(format!("nbt: unknown tag id {}", id))),
+    };
+    Ok(tag)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let [b] = read_array::<R, 1>(reader)?;
+    Ok(b)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_len<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let len = i32::from_be_bytes(read_array(reader)?);
+    if len < 0 {
+        return Err(invalid(format!("nbt: negative length {}", len)));
+    }
+    Ok(len as usize)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = u16::from_be_bytes(read_array(reader)?) as usize;
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
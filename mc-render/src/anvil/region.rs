@@ -0,0 +1,83 @@
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+use flate2::read::GzDecoder;
+use flate2::read::ZlibDecoder;
+
+use super::nbt;
+use super::nbt::Tag;
+
+
+const SECTOR: u64 = 4096;
+
+/**
+ *  `r.<x>.<z>.mca`: 32x32 chunks, each stored as compressed nbt in 4KiB sectors
+ */
+pub struct Region<R: Read + Seek> {
+
+    reader: R,
+
+    locations: Vec<u32>,
+
+}
+
+impl<R: Read + Seek> Region<R> {
+
+    /**
+     *  a file shorter than the header, like the empty ones the game leaves behind, has no chunks
+     */
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(SECTOR as usize);
+        reader.seek(SeekFrom::Start(0))?;
+        (&mut reader).take(SECTOR).read_to_end(&mut header)?;
+        let locations = if header.len() < SECTOR as usize {
+            vec![0; 1024]
+        } else {
+            header.chunks(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]])).collect()
+        };
+        Ok(Region {
+            reader,
+            locations
+        })
+    }
+
+    fn location(&self, cx: usize, cz: usize) -> u32 {
+        if cx < 32 && cz < 32 {
+            self.locations[cx + cz * 32]
+        } else {
+            0
+        }
+    }
+
+    /**
+     *  `cx`, `cz` are chunk coordinates inside the region, 0..32; there is no chunk outside
+     */
+    pub fn has_chunk(&self, cx: usize, cz: usize) -> bool {
+        self.location(cx, cz) != 0
+    }
+
+    pub fn read_chunk(&mut self, cx: usize, cz: usize) -> io::Result<Option<Tag>> {
+        let location = self.location(cx, cz);
+        if location == 0 {
+            return Ok(None);
+        }
+        let offset = (location >> 8) as u64 * SECTOR;
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut head = [0u8; 5];
+        self.reader.read_exact(&mut head)?;
+        let len = u32::from_be_bytes([head[0], head[1], head[2], head[3]]) as u64;
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut data = (&mut self.reader).take(len - 1);
+        let (_, tag) = match head[4] {
+            1 => nbt::read(&mut GzDecoder::new(&mut data))?,
+            2 => nbt::read(&mut ZlibDecoder::new(&mut data))?,
+            3 => nbt::read(&mut data)?,
+            c => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported chunk compression {}", c))),
+        };
+        Ok(Some(tag))
+    }
+}
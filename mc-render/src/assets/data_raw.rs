@@ -95,8 +95,8 @@ impl Merge for ModelRaw {
                 }
             }
         } else {
-            if other.display.is_some() {
-                self.display = other.display.clone();
+            if other.textures.is_some() {
+                self.textures = other.textures.clone();
             }
         }
         true
@@ -449,13 +449,6 @@ impl ApplyRaw {
         }
     }
 
-    pub fn get_fast(&self) -> &AppliedModelRaw {
-        match self {
-            Self::Item(p) => p,
-            Self::Array(list) => &list[0],
-        }
-    }
-
 }
 
 /**
@@ -809,6 +802,32 @@ impl<'de> Deserialize<'de> for BlockStateRaw {
 
 
 
+/**
+ *  one block of the `reports/blocks.json` the vanilla data generator writes
+ */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BlockReportRaw {
+
+    #[serde(default)]
+    pub properties: Map<String, Vec<String>>,
+
+    #[serde(default)]
+    pub states: Vec<StateReportRaw>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StateReportRaw {
+
+    #[serde(default)]
+    pub properties: Map<String, String>,
+
+    pub id: u32,
+
+    #[serde(default)]
+    pub default: bool,
+}
+
+
 pub fn transform(axis: Axis, rotation: Rotate90, vec3: &[f32; 3]) -> [f32; 3] {
     match rotation {
         Rotate90::R0 => {
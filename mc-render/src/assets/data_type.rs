@@ -18,6 +18,21 @@ pub enum Axis {
     Z = 2
 }
 
+impl Axis {
+
+    /**
+     *  the value of an `axis` blockstate property
+     */
+    pub fn from_name(name: &str) -> Option<Axis> {
+        match name {
+            "x" => Some(Axis::X),
+            "y" => Some(Axis::Y),
+            "z" => Some(Axis::Z),
+            _ => None,
+        }
+    }
+}
+
 
 /**
  * 
@@ -49,6 +64,8 @@ pub enum Face {
 
 impl Face {
 
+    pub const ALL: [Face; 6] = [Face::West, Face::Down, Face::North, Face::South, Face::Up, Face::East];   // in `index` order
+
     pub fn index(&self) -> usize {
         match self {
             Self::West => 0,
@@ -60,6 +77,21 @@ impl Face {
         }
     }
 
+    /**
+     *  the value of a `facing` blockstate property, or a face name in a model
+     */
+    pub fn from_name(name: &str) -> Option<Face> {
+        match name {
+            "down" => Some(Face::Down),
+            "up" => Some(Face::Up),
+            "north" => Some(Face::North),
+            "south" => Some(Face::South),
+            "west" => Some(Face::West),
+            "east" => Some(Face::East),
+            _ => None,
+        }
+    }
+
     pub fn opposite(&self) -> Face {
         match self {
             Self::West => Self::East,
@@ -103,6 +135,13 @@ impl Rotate90 {
         }
     }
 
+    /**
+     *  quarter turns, any number of them
+     */
+    pub fn from_index(index: usize) -> Self {
+        ROTATE90_ADD[index % 4].clone()
+    }
+
 }
 
 const ROTATE90_ADD: [Rotate90; 7] = [Rotate90::R0,Rotate90::R90,Rotate90::R180,Rotate90::R270,Rotate90::R0,Rotate90::R90,Rotate90::R180];
@@ -6,3 +6,4 @@ pub mod data_type;
 pub mod data_raw;
 pub mod biome;
 
+pub(crate) const LOG_TARGET: &str = "mc_render::assets";
@@ -6,12 +6,17 @@ use std::io::Seek;
 use zip::read::ZipArchive;
 use zip::read::ZipFile;
 use zip::result::ZipResult;
+use zip::result::ZipError;
 use serde_json;
 use image;
 use image::RgbaImage;
 use image::ConvertBuffer;
+use log::debug;
+use log::warn;
+use super::LOG_TARGET;
 use super::util::Scanner;
 use super::util::Provider;
+use super::util::ProvideError;
 use super::data_raw::ModelRaw;
 use super::data_raw::BlockStateRaw;
 
@@ -40,7 +45,6 @@ impl<R: Read + Seek> AssetsArchive<R> {
     }
 
     pub fn by_name<'a>(&'a mut self, name: &str) -> ZipResult<ZipFile<'a>> {
-        use zip::result::ZipError;
         let mut err = ZipError::FileNotFound;
         for zip in self.zips.iter_mut() {
             match zip.by_name(name) {
@@ -89,6 +93,17 @@ impl<R: Read + Seek> AssetsArchive<R> {
 
 
 
+impl From<ZipError> for ProvideError {
+
+    fn from(e: ZipError) -> Self {
+        match e {
+            ZipError::FileNotFound => ProvideError::NotFound,
+            e => ProvideError::Invalid(e.to_string()),
+        }
+    }
+}
+
+
 /**
  * 
  */
@@ -112,24 +127,23 @@ impl<R: Read + Seek> Provider for ModelRawProvider<R> {
     type Item = ModelRaw;
 
     fn provide(&mut self, name: &str) -> Option<Self::Item> {
-        let full = format!("assets/minecraft/models/{}.json", name);
-        match self.zip.borrow_mut().by_name(&full) {
-            Ok(v) => match serde_json::from_reader(v) {
-                Ok(v) => {
-                    self.count += 1;
-                    Some(v)
-                },
-                Err(e) => {
-                    //TODO: log
-                    return None 
-                }
-            },
+        match self.try_provide(name) {
+            Ok(v) => Some(v),
             Err(e) => {
-                //TODO: log
-                return None 
+                warn!(target: LOG_TARGET, "model `{}`: {}", name, e);
+                None
             }
         }
     }
+
+    fn try_provide(&mut self, name: &str) -> Result<Self::Item, ProvideError> {
+        let full = format!("assets/minecraft/models/{}.json", name);
+        let mut zip = self.zip.borrow_mut();
+        let v = zip.by_name(&full).map_err(ProvideError::from)?;
+        let v = serde_json::from_reader(v).map_err(|e| ProvideError::Invalid(e.to_string()))?;
+        self.count += 1;
+        Ok(v)
+    }
 }
 
 
@@ -156,23 +170,22 @@ impl<R: Read + Seek> Provider for BlockStateRawProvider<R> {
     type Item = BlockStateRaw;
 
     fn provide(&mut self, name: &str) -> Option<Self::Item> {
-        let full = format!("assets/minecraft/blockstates/{}.json", name);
-        match self.zip.borrow_mut().by_name(&full) {
-            Ok(v) => match serde_json::from_reader(v) {
-                Ok(v) => {
-                    self.count += 1;
-                    Some(v)
-                },
-                Err(e) => {
-                    //TODO: log
-                    None 
-                }
-            },
+        match self.try_provide(name) {
+            Ok(v) => Some(v),
             Err(e) => {
-                //TODO: log
-                None 
+                warn!(target: LOG_TARGET, "blockstate `{}`: {}", name, e);
+                None
             }
-        } 
+        }
+    }
+
+    fn try_provide(&mut self, name: &str) -> Result<Self::Item, ProvideError> {
+        let full = format!("assets/minecraft/blockstates/{}.json", name);
+        let mut zip = self.zip.borrow_mut();
+        let v = zip.by_name(&full).map_err(ProvideError::from)?;
+        let v = serde_json::from_reader(v).map_err(|e| ProvideError::Invalid(e.to_string()))?;
+        self.count += 1;
+        Ok(v)
     }
 }
 
@@ -216,22 +229,22 @@ impl<'a, R: Read + Seek> Provider for TextureImageProvider<R> {
                             v.convert()
                         },
                         _ => {
-                            //TODO: log
+                            warn!(target: LOG_TARGET, "texture `{}`: unsupported color type", full);
                             return None;
                         }
                     },
                     Err(e) => {
-                        //TODO: log
+                        warn!(target: LOG_TARGET, "texture `{}`: {}", full, e);
                         return None;
                     }
                 },
                 Err(e) => {
-                    //TODO: log
+                    warn!(target: LOG_TARGET, "texture `{}`: {}", full, e);
                     return None;
                 }
             },
             Err(e) => {
-                //TODO: log
+                warn!(target: LOG_TARGET, "texture `{}`: {}", full, e);
                 return None;
             }
         };
@@ -239,7 +252,7 @@ impl<'a, R: Read + Seek> Provider for TextureImageProvider<R> {
             let width = img.width();
             let mut buf = img.into_raw();
             buf.truncate(width as usize * width as usize * 4);
-            println!("animated: {} ({})", name, width);
+            debug!(target: LOG_TARGET, "animated: {} ({})", name, width);
             Some(RgbaImage::from_vec(width, width, buf).unwrap())
         } else {
             Some(img)
@@ -117,10 +117,45 @@ pub fn merge(a: &mut Value, b: Value) {
  * 
  */
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProvideError {
+    NotFound,
+    Invalid(String),
+}
+
+impl std::fmt::Display for ProvideError {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "not found"),
+            Self::Invalid(s) => write!(f, "{}", s),
+        }
+    }
+}
+
 pub trait Provider {
     type Item;
 
     fn provide(&mut self, name: &str) -> Option<Self::Item>;
 
+    /**
+     *  same as `provide`, but tells a missing entry apart from a broken one
+     */
+    fn try_provide(&mut self, name: &str) -> Result<Self::Item, ProvideError> {
+        self.provide(name).ok_or(ProvideError::NotFound)
+    }
+
+}
+
+impl<P: Provider + ?Sized> Provider for &mut P {
+    type Item = P::Item;
+
+    fn provide(&mut self, name: &str) -> Option<Self::Item> {
+        (**self).provide(name)
+    }
+
+    fn try_provide(&mut self, name: &str) -> Result<Self::Item, ProvideError> {
+        (**self).try_provide(name)
+    }
 }
 
@@ -0,0 +1,65 @@
+use image::Rgba;
+use image::RgbaImage;
+
+
+/**
+ *  smoother edges: `msaa` samples per pixel, and/or drawing `supersample` times as large and filtering down
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Antialias {
+
+    pub msaa: u32,          // 0 or 1 for none
+
+    pub supersample: u32,   // 1 for none
+
+}
+
+impl Default for Antialias {
+
+    fn default() -> Self {
+        Antialias {
+            msaa: 0,
+            supersample: 1,
+        }
+    }
+}
+
+impl Antialias {
+
+    /**
+     *  whether images can be drawn straight into the window
+     */
+    pub fn is_none(&self) -> bool {
+        self.msaa <= 1 && self.supersample <= 1
+    }
+}
+
+/**
+ *  `image` shrunk by `k` in both directions, each pixel the mean of its `k` by `k` block; colours are weighted
+ *  by alpha, so transparent pixels do not darken the edges. the size should be a multiple of `k`
+ */
+pub fn downsample(image: &RgbaImage, k: u32) -> RgbaImage {
+    if k <= 1 {
+        return image.clone();
+    }
+    let (w, h) = (image.width() / k, image.height() / k);
+    let n = (k * k) as f32;
+    RgbaImage::from_fn(w, h, |x, y| {
+        let mut sum = [0.0f32; 4];
+        for dy in 0..k {
+            for dx in 0..k {
+                let p = image.get_pixel(x * k + dx, y * k + dy);
+                let a = f32::from(p[3]);
+                for c in 0..3 {
+                    sum[c] += f32::from(p[c]) * a;
+                }
+                sum[3] += a;
+            }
+        }
+        if sum[3] == 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let f = |c: f32| (c / sum[3]).round().min(255.0) as u8;
+        Rgba([f(sum[0]), f(sum[1]), f(sum[2]), (sum[3] / n).round() as u8])
+    })
+}
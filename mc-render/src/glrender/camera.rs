@@ -0,0 +1,192 @@
+use cgmath::Deg;
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::Matrix4;
+use cgmath::InnerSpace;
+use cgmath::EuclideanSpace;
+use cgmath::SquareMatrix;
+use cgmath::Vector4;
+
+use crate::assets::data_type::Face;
+use crate::model::block::BlockBounds;
+
+
+const ISOMETRIC_PITCH: f32 = 35.264_39;    // atan(1 / sqrt(2))
+
+const NEAR: f32 = 0.05;
+
+/**
+ *  the renderer keeps the fragment with the greatest depth, so nearer has to map to +1
+ */
+fn flip_z() -> Matrix4<f32> {
+    Matrix4::from_nonuniform_scale(1.0, 1.0, -1.0)
+}
+
+
+fn face_normal(face: &Face) -> Vector3<f32> {
+    match face {
+        Face::West => -Vector3::unit_x(),
+        Face::Down => -Vector3::unit_y(),
+        Face::North => -Vector3::unit_z(),
+        Face::South => Vector3::unit_z(),
+        Face::Up => Vector3::unit_y(),
+        Face::East => Vector3::unit_x(),
+    }
+}
+
+
+/**
+ *  view + projection for `OffScreenRenderer`; `matrix` works in blocks relative to `center`,
+ *  `bounds` holds every block that can appear in the picture
+ */
+#[derive(Clone, Debug)]
+pub struct Camera {
+
+    matrix: Matrix4<f32>,
+
+    center: Vector3<i32>,
+
+    bounds: BlockBounds,
+
+    facing: [bool; 6],      // by `Face::index`
+
+}
+
+impl Camera {
+
+    /**
+     *  looking straight down, east to the right and north up
+     */
+    pub fn top_down(bounds: BlockBounds, aspect: f32) -> Self {
+        Camera::orthographic(Matrix4::from_angle_x(Deg(90.0)), bounds, aspect)
+    }
+
+    /**
+     *  looking north-west and down from the south-east, the classic 2:1 isometric view
+     */
+    pub fn isometric(bounds: BlockBounds, aspect: f32) -> Self {
+        let rotation = Matrix4::from_angle_x(Deg(ISOMETRIC_PITCH)) * Matrix4::from_angle_y(Deg(-45.0));
+        Camera::orthographic(rotation, bounds, aspect)
+    }
+
+    /**
+     *  parallel projection along `rotation` (view space looks down -z) fitted around `bounds`,
+     *  widened to match `aspect` (width / height)
+     */
+    pub fn orthographic(rotation: Matrix4<f32>, bounds: BlockBounds, aspect: f32) -> Self {
+        let center = bounds.center();
+        let mut lo = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut hi = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+        for i in 0 .. 8 {
+            let corner = Vector3::new(
+                if i & 1 == 0 { bounds.min.x } else { bounds.max.x },
+                if i & 2 == 0 { bounds.min.y } else { bounds.max.y },
+                if i & 4 == 0 { bounds.min.z } else { bounds.max.z },
+            );
+            let p = rotation * (corner - center).cast::<f32>().unwrap().extend(1.0);
+            lo = Vector3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z));
+            hi = Vector3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z));
+        }
+        let mut hw = lo.x.abs().max(hi.x.abs());
+        let mut hh = lo.y.abs().max(hi.y.abs());
+        if hw < hh * aspect {
+            hw = hh * aspect;
+        } else {
+            hh = hw / aspect;
+        }
+        let projection = cgmath::ortho(-hw, hw, -hh, hh, -hi.z, -lo.z);
+        // towards the camera is view space +z
+        let back = rotation.invert().map(|m| m * Vector4::unit_z()).unwrap_or_else(Vector4::unit_z);
+        let mut facing = [false; 6];
+        for face in Face::ALL.iter() {
+            let n = face_normal(face);
+            facing[face.index()] = n.x * back.x + n.y * back.y + n.z * back.z > 1e-4;
+        }
+        Camera {
+            matrix: flip_z() * projection * rotation,
+            center,
+            bounds,
+            facing,
+        }
+    }
+
+    /**
+     *  pinhole camera at `eye` looking at `target`; `fov` is vertical, nothing beyond `far` blocks is drawn
+     */
+    pub fn perspective(eye: Point3<f32>, target: Point3<f32>, fov: Deg<f32>, aspect: f32, far: f32) -> Self {
+        let center = Vector3::new(eye.x.floor() as i32, eye.y.floor() as i32, eye.z.floor() as i32);
+        let offset = center.cast::<f32>().unwrap();
+        let forward = (target - eye).normalize();
+        let up = if forward.x.abs() < 1e-4 && forward.z.abs() < 1e-4 {
+            -Vector3::unit_z()
+        } else {
+            Vector3::unit_y()
+        };
+        let view = Matrix4::look_at(eye - offset, target - offset, up);
+        let projection = cgmath::perspective(fov, aspect, NEAR, far);
+
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        let tan = (fov.0 / 2.0).to_radians().tan();
+        let mut lo = eye.to_vec();
+        let mut hi = eye.to_vec();
+        for i in 0 .. 4 {
+            let sx = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let sy = if i & 2 == 0 { -1.0 } else { 1.0 };
+            let p = eye.to_vec() + (forward + right * (sx * tan * aspect) + up * (sy * tan)) * far;
+            lo = Vector3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z));
+            hi = Vector3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z));
+        }
+        let bounds = BlockBounds::new(
+            Vector3::new(lo.x.floor() as i32, lo.y.floor() as i32, lo.z.floor() as i32),
+            Vector3::new(hi.x.ceil() as i32 + 1, hi.y.ceil() as i32 + 1, hi.z.ceil() as i32 + 1),
+        );
+        Camera {
+            matrix: flip_z() * projection * view,
+            center,
+            bounds,
+            facing: [true; 6],
+        }
+    }
+
+    /**
+     *  the block at the origin through a model display `transform` (see `model::Display`),
+     *  filling the picture the way a 16x16 inventory slot does
+     */
+    pub fn display(transform: Matrix4<f32>, aspect: f32) -> Self {
+        let projection = cgmath::ortho(-0.5 * aspect, 0.5 * aspect, -0.5, 0.5, -1.0, 1.0);
+        Camera {
+            matrix: flip_z() * projection * transform,
+            center: Vector3::new(0, 0, 0),
+            bounds: BlockBounds::new(Vector3::new(0, 0, 0), Vector3::new(1, 1, 1)),
+            facing: [true; 6],
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        self.matrix
+    }
+
+    pub fn center(&self) -> Vector3<i32> {
+        self.center
+    }
+
+    pub fn bounds(&self) -> BlockBounds {
+        self.bounds
+    }
+
+    /**
+     *  the faces that can turn towards the camera, the ones worth meshing: `Up` alone looking straight
+     *  down, three of them for the isometric view, all six for a perspective camera
+     */
+    pub fn faces(&self) -> Vec<Face> {
+        Face::ALL.iter().filter(|face| self.facing[face.index()]).cloned().collect()
+    }
+
+    /**
+     *  false if the block at `loc` is certainly off screen
+     */
+    pub fn contains(&self, loc: &Vector3<i32>) -> bool {
+        self.bounds.contains(loc)
+    }
+}
@@ -0,0 +1,97 @@
+use image::RgbaImage;
+
+
+/**
+ *  bloom around light sources: what the block light lights up, blurred and added onto the image
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glow {
+
+    pub radius: u32,        // in pixels, about how far the glow reaches
+
+    pub strength: f32,      // 1.0 adds the blurred light as it is
+
+    pub threshold: u8,      // block light the glow starts at, 1 to 15
+
+}
+
+impl Default for Glow {
+
+    fn default() -> Self {
+        Glow {
+            radius: 8,
+            strength: 1.0,
+            threshold: 12,
+        }
+    }
+}
+
+impl Glow {
+
+    /**
+     *  blur `mask`, the glow pass rendered the same size as `image`, and add it onto `image`;
+     *  transparent pixels take on as much alpha as the glow needs
+     */
+    pub fn apply(&self, image: &mut RgbaImage, mask: &RgbaImage) {
+        let (w, h) = image.dimensions();
+        assert_eq!((w, h), mask.dimensions());
+        let (w, h) = (w as usize, h as usize);
+        let mut glow: Vec<[f32; 3]> = mask.pixels().map(|p| [f32::from(p[0]), f32::from(p[1]), f32::from(p[2])]).collect();
+        // three box blurs are close enough to a gaussian
+        let half = (self.radius / 3).max(1) as usize;
+        for _ in 0..3 {
+            box_blur(&mut glow, w, h, half, 1, w);
+            box_blur(&mut glow, h, w, half, w, 1);
+        }
+        for (p, g) in image.pixels_mut().zip(glow.iter()) {
+            let add = [g[0] * self.strength, g[1] * self.strength, g[2] * self.strength];
+            let a = f32::from(p[3]);
+            let alpha = add.iter().fold(a, |alpha, c| alpha.max(*c)).min(255.0);
+            if alpha == 0.0 {
+                continue;
+            }
+            for i in 0..3 {
+                let premultiplied = f32::from(p[i]) * a / 255.0 + add[i];
+                p[i] = (premultiplied * 255.0 / alpha).min(255.0).round() as u8;
+            }
+            p[3] = alpha.round() as u8;
+        }
+    }
+}
+
+/**
+ *  blur each of the `lines` lines of `len` pixels, `step` apart along the line and `stride` apart across,
+ *  by the mean of the `2 * half + 1` pixels around; nothing beyond the edges
+ */
+fn box_blur(data: &mut [[f32; 3]], len: usize, lines: usize, half: usize, step: usize, stride: usize) {
+    let n = (2 * half + 1) as f32;
+    let mut line = vec![[0.0f32; 3]; len];
+    for l in 0..lines {
+        let base = l * stride;
+        for (i, v) in line.iter_mut().enumerate() {
+            *v = data[base + i * step];
+        }
+        let mut sum = [0.0f32; 3];
+        for v in line.iter().take(half) {
+            for c in 0..3 {
+                sum[c] += v[c];
+            }
+        }
+        for i in 0..len {
+            if i + half < len {
+                for c in 0..3 {
+                    sum[c] += line[i + half][c];
+                }
+            }
+            if i > half {
+                for c in 0..3 {
+                    sum[c] -= line[i - half - 1][c];
+                }
+            }
+            let out = &mut data[base + i * step];
+            for c in 0..3 {
+                out[c] = sum[c] / n;
+            }
+        }
+    }
+}
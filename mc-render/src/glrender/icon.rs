@@ -0,0 +1,125 @@
+use std::slice::Iter as SliceIter;
+
+use cgmath::Vector3;
+use cgmath::Matrix4;
+
+use image::RgbaImage;
+
+use crate::assets::data_type::Face;
+use crate::model;
+use crate::model::model::WeightedModel;
+use crate::model::model::Display;
+use crate::model::model::DisplayContext;
+use crate::model::blockstate::Weighted;
+use crate::model::block::World;
+use crate::model::block::RenderableBlock;
+use super::GEResult;
+use super::MeshGenerator;
+use super::OffScreenRenderer;
+use super::camera::Camera;
+use super::context::Context;
+use super::texture::CombinedTexture;
+use super::mesh::Mesh;
+use super::mesh::MeshVertex;
+
+
+const FULL_LIGHT: u8 = 0xFF;
+
+/**
+ *  nothing but one block at the origin
+ */
+struct IconWorld<'a> {
+    models: &'a [WeightedModel<CombinedTexture>],
+    color: [u8; 4],
+}
+
+#[derive(Clone, Copy)]
+struct IconBlock<'a> {
+    models: &'a [WeightedModel<CombinedTexture>],
+    color: [u8; 4],
+}
+
+impl<'a> RenderableBlock<'a> for IconBlock<'a> {
+    type Model = WeightedModel<CombinedTexture>;
+
+    fn is_air(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    fn is_water(&self) -> bool {
+        false
+    }
+
+    fn is_water_logged(&self) -> bool {
+        false
+    }
+
+    fn get_models(&self) -> SliceIter<'a, Self::Model> {
+        self.models.iter()
+    }
+
+    fn get_water_models(&self) -> SliceIter<'a, Self::Model> {
+        self.models[0..0].iter()
+    }
+
+    fn get_inline_color(&self, tintindex: usize) -> [u8; 4] {
+        let _ = tintindex;
+        self.color
+    }
+
+    fn get_light(&self) -> u8 {
+        FULL_LIGHT
+    }
+}
+
+impl<'a> World<'a> for IconWorld<'a> {
+    type Block = IconBlock<'a>;
+
+    fn get(&'a self, loc: &Vector3<i32>) -> Self::Block {
+        let models = if *loc == Vector3::new(0, 0, 0) { self.models } else { &self.models[0..0] };
+        IconBlock { models, color: self.color }
+    }
+}
+
+
+/**
+ *  `context` transform of the first model that has one; without any the block is only centered
+ */
+pub fn display_transform(models: &[WeightedModel<CombinedTexture>], context: DisplayContext) -> Matrix4<f32> {
+    models.iter()
+        .filter_map(Weighted::first)
+        .find_map(|tmodel| tmodel.model.display.get(context))
+        .unwrap_or_else(|| Display::default().transform(context))
+}
+
+/**
+ *  all faces of `models` placed at the origin; `color` tints every face with a `tintindex`
+ */
+pub fn icon_meshes(models: &[WeightedModel<CombinedTexture>], color: [u8; 4]) -> GEResult<Vec<Mesh<MeshVertex>>> {
+    let world = IconWorld { models, color };
+    let mut r = MeshGenerator::new();
+    model::draw(&Face::ALL, &Vector3::new(0, 0, 0), &mut r, &world)?;
+    Ok(r.unwrap())
+}
+
+
+impl<'a, C: Context> OffScreenRenderer<'a, C> {
+
+    /**
+     *  one blockstate held, worn or framed as `context` says, on a transparent background
+     *  the size of the context
+     */
+    pub fn draw_display(&mut self, models: &[WeightedModel<CombinedTexture>], context: DisplayContext, color: [u8; 4]) -> GEResult<RgbaImage> {
+        let meshes = icon_meshes(models, color)?;
+        let camera = Camera::display(display_transform(models, context), 1.0);
+        self.draw_camera(meshes.iter(), &camera)
+    }
+
+    /**
+     *  inventory icon of one blockstate, see `draw_display`
+     */
+    pub fn draw_icon(&mut self, models: &[WeightedModel<CombinedTexture>], color: [u8; 4]) -> GEResult<RgbaImage> {
+        self.draw_display(models, DisplayContext::Gui, color)
+    }
+
+}
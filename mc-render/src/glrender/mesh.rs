@@ -8,11 +8,12 @@ use glium::uniforms::Sampler;
 use glium::backend::Facade;
 use glium::program::Program;
 use glium::draw_parameters::DrawParameters;
-use glium::Frame;
 use glium::Surface;
 use glium::texture::Texture2d;
 use glium::texture::Texture2dArray;
 
+use crate::model::model::RenderLayer;
+
 
 pub type GEResult<T> = Result<T, Box<dyn std::error::Error>>;
 
@@ -75,6 +76,10 @@ pub struct MeshUniform<'a> {
 
     pub light_map: Sampler<'a, Texture2d>,
 
+    pub layer: i32,             // `RenderLayer::priority`
+
+    pub glow: i32,              // `Glow::threshold` in the glow pass, 0 otherwise
+
 }
 
 impl<'b> Uniforms for MeshUniform<'b> {
@@ -84,6 +89,8 @@ impl<'b> Uniforms for MeshUniform<'b> {
         output("center", self.center.as_uniform_value());
         output("textures", self.textures.as_uniform_value());
         output("light_map", self.light_map.as_uniform_value());
+        output("layer", self.layer.as_uniform_value());
+        output("glow", self.glow.as_uniform_value());
     }
 }
 
@@ -94,6 +101,8 @@ pub struct Mesh<V> {
 
     indices: Vec<u16>,
 
+    layer: RenderLayer,
+
 }
 
 impl<V: Vertex> Mesh<V> {
@@ -102,6 +111,7 @@ impl<V: Vertex> Mesh<V> {
         Mesh {
             vertexs: Vec::new(),
             indices: Vec::new(),
+            layer: RenderLayer::Solid,
         }
     }
 
@@ -109,6 +119,7 @@ impl<V: Vertex> Mesh<V> {
         Mesh {
             vertexs: Vec::with_capacity(capacity.0),
             indices: Vec::with_capacity(capacity.1),
+            layer: RenderLayer::Solid,
         }
     }
 
@@ -116,9 +127,18 @@ impl<V: Vertex> Mesh<V> {
         Mesh {
             vertexs: Vec::with_capacity(n * 4),
             indices: Vec::with_capacity(n * 6),
+            layer: RenderLayer::Solid,
         }
     }
 
+    pub fn layer(&self) -> RenderLayer {
+        self.layer
+    }
+
+    pub fn set_layer(&mut self, layer: RenderLayer) {
+        self.layer = layer;
+    }
+
     pub fn size(&self) -> (usize, usize) {
         (self.vertexs.len(), self.indices.len())
     }
@@ -133,10 +153,34 @@ impl<V: Vertex> Mesh<V> {
         self.indices.extend(&indices[0..6]);
     }
 
-    pub fn draw<F: Facade, U: Uniforms>(&self, facade: &F, frame: &mut Frame, program: &Program, uniforms: &U, draw_parameters: &DrawParameters) -> GEResult<()> {
+    /**
+     *  the vertices of each quad, in the order they were appended
+     */
+    pub fn quads(&self) -> std::slice::Chunks<'_, V> {
+        self.vertexs.chunks(4)
+    }
+
+    /**
+     *  indices with the quads ordered by ascending `key`
+     */
+    pub fn sorted_indices<K: Fn(&[V]) -> f32>(&self, key: K) -> Vec<u16> {
+        let mut quads: Vec<(f32, u16)> = self.vertexs.chunks(4).enumerate().map(|(i, q)| (key(q), (i * 4) as u16)).collect();
+        quads.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for (_, base) in quads {
+            indices.extend(&[base, base + 1, base + 2, base + 3, base + 2, base + 1]);
+        }
+        indices
+    }
+
+    pub fn draw<F: Facade, S: Surface, U: Uniforms>(&self, facade: &F, frame: &mut S, program: &Program, uniforms: &U, draw_parameters: &DrawParameters) -> GEResult<()> {
+        self.draw_with_indices(facade, frame, program, uniforms, draw_parameters, self.indices.as_slice())
+    }
+
+    pub fn draw_with_indices<F: Facade, S: Surface, U: Uniforms>(&self, facade: &F, frame: &mut S, program: &Program, uniforms: &U, draw_parameters: &DrawParameters, indices: &[u16]) -> GEResult<()> {
         if self.vertexs.len() > 0 {
             let vbuf = glium::VertexBuffer::immutable(facade, self.vertexs.as_slice()).map_err(Box::new)?;
-            let ibuf = glium::IndexBuffer::immutable(facade, glium::index::PrimitiveType::TrianglesList, self.indices.as_slice()).map_err(Box::new)?;
+            let ibuf = glium::IndexBuffer::immutable(facade, glium::index::PrimitiveType::TrianglesList, indices).map_err(Box::new)?;
             frame.draw(&vbuf, &ibuf, program, uniforms, draw_parameters).map_err(Box::new)?;
         }
         Ok(())
@@ -1,4 +1,8 @@
+pub mod antialias;
+pub mod camera;
 pub mod context;
+pub mod glow;
+pub mod icon;
 pub mod mesh;
 pub mod texture;
 
@@ -12,6 +16,14 @@ use glium::Surface;
 use glium::texture::RawImage2d;
 use glium::texture::Texture2d;
 use glium::texture::Texture2dArray;
+use glium::texture::Texture2dMultisample;
+use glium::texture::DepthTexture2d;
+use glium::texture::DepthTexture2dMultisample;
+use glium::texture::DepthFormat;
+use glium::texture::MipmapsOption;
+use glium::texture::UncompressedFloatFormat;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::BlitTarget;
 use glium::program::Program;
 use glium::draw_parameters::DrawParameters;
 use glium::uniforms::Sampler;
@@ -25,7 +37,11 @@ use cgmath::Vector3;
 use cgmath::Matrix4;
 
 use crate::model::BlockRenderer;
+use crate::model::model::RenderLayer;
+use antialias::Antialias;
+use camera::Camera;
 use context::Context;
+use glow::Glow;
 use texture::CombinedTexture;
 use texture::RgbaTexture2d;
 use mesh::MeshVertex;
@@ -34,6 +50,8 @@ use mesh::MeshUniform;
 
 pub type GEResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+pub(crate) const LOG_TARGET: &str = "mc_render::glrender";
+
 pub struct OffScreenRenderer<'a, C: Context> {
 
     ctx: &'a C,
@@ -46,6 +64,10 @@ pub struct OffScreenRenderer<'a, C: Context> {
 
     draw_params: DrawParameters<'a>,
 
+    glow: Option<Glow>,
+
+    antialias: Antialias,
+
 }
 
 
@@ -86,35 +108,147 @@ impl<'a, C: Context> OffScreenRenderer<'a, C> {
                 },
                 blend: glium::Blend::alpha_blending(),
                 .. Default::default()
-            }
+            },
+            glow: None,
+            antialias: Antialias::default(),
         }
     }
 
+    /**
+     *  with any anti-aliasing, images are drawn into textures instead of the window
+     */
+    pub fn set_antialias(&mut self, antialias: Antialias) {
+        self.antialias = antialias;
+    }
+
+    /**
+     *  glow around light sources on every image drawn after, at the cost of a second pass
+     */
+    pub fn set_glow(&mut self, glow: Option<Glow>) {
+        self.glow = glow;
+    }
+
     pub fn draw<'b, I>(&mut self, meshes: I, world: Matrix4<f32>, center: Vector3<i32>) -> GEResult<RgbaImage>
     where
         I: Iterator<Item=&'b Mesh<MeshVertex>> 
     {
-        let mut frame = self.ctx.surface();
-        frame.clear_all((0.0, 0.0, 0.0, 0.0), -1.0, 0);
-        let uniforms = MeshUniform {
-            world: world.into(),
-            center: center.into(),
-            textures: self.sampled_textures,
-            light_map: self.sampled_light_map,
+        let mut meshes: Vec<_> = meshes.collect();
+        meshes.sort_by_key(|mesh| mesh.layer());
+        let translucent = sort_translucent(&meshes, world, center);
+        meshes.retain(|mesh| mesh.layer() != RenderLayer::Translucent);
+        let mut image = self.render(&meshes, &translucent, world, center, 0)?;
+        if let Some(glow) = self.glow {
+            let mask = self.render(&meshes, &translucent, world, center, i32::from(glow.threshold.max(1)))?;
+            glow.apply(&mut image, &mask);
+        }
+        Ok(image)
+    }
+
+    /**
+     *  one pass over the `opaque` meshes, sorted by layer, then the sorted `translucent` ones, as large as
+     *  the window; the glow pass if `glow` is not 0
+     */
+    fn render(&self, opaque: &[&Mesh<MeshVertex>], translucent: &[Mesh<MeshVertex>], world: Matrix4<f32>, center: Vector3<i32>, glow: i32) -> GEResult<RgbaImage> {
+        if self.antialias.is_none() {
+            let mut frame = self.ctx.surface();
+            self.render_to(&mut frame, opaque, translucent, world, center, glow)?;
+            frame.finish().map_err(Box::new)?;
+            let raw2d: RgbaTexture2d = self.ctx.context().read_front_buffer().map_err(Box::new)?;
+            return Ok(raw2d.inner());
+        }
+        let facade = self.ctx.facade();
+        let scale = self.antialias.supersample.max(1);
+        let (width, height) = self.ctx.context().get_framebuffer_dimensions();
+        let (w, h) = (width * scale, height * scale);
+        let color = Texture2d::empty_with_format(facade, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, w, h).map_err(Box::new)?;
+        let samples = self.antialias.msaa;
+        if samples > 1 {
+            let ms_color = Texture2dMultisample::empty_with_format(facade, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, w, h, samples).map_err(Box::new)?;
+            let ms_depth = DepthTexture2dMultisample::empty_with_format(facade, DepthFormat::I24, MipmapsOption::NoMipmap, w, h, samples).map_err(Box::new)?;
+            let mut fb = SimpleFrameBuffer::with_depth_buffer(facade, &ms_color, &ms_depth).map_err(Box::new)?;
+            self.render_to(&mut fb, opaque, translucent, world, center, glow)?;
+            // resolve the samples
+            let target = SimpleFrameBuffer::new(facade, &color).map_err(Box::new)?;
+            let rect = BlitTarget { left: 0, bottom: 0, width: w as i32, height: h as i32 };
+            fb.blit_whole_color_to(&target, &rect, MagnifySamplerFilter::Nearest);
+        } else {
+            let depth = DepthTexture2d::empty_with_format(facade, DepthFormat::I24, MipmapsOption::NoMipmap, w, h).map_err(Box::new)?;
+            let mut fb = SimpleFrameBuffer::with_depth_buffer(facade, &color, &depth).map_err(Box::new)?;
+            self.render_to(&mut fb, opaque, translucent, world, center, glow)?;
+        }
+        let raw2d: RgbaTexture2d = color.read();
+        Ok(antialias::downsample(&raw2d.inner(), scale))
+    }
+
+    fn render_to<S: Surface>(&self, surface: &mut S, opaque: &[&Mesh<MeshVertex>], translucent: &[Mesh<MeshVertex>], world: Matrix4<f32>, center: Vector3<i32>, glow: i32) -> GEResult<()> {
+        surface.clear_all((0.0, 0.0, 0.0, 0.0), -1.0, 0);
+        let translucent_params = DrawParameters {
+            depth: glium::Depth {
+                write: false,
+                .. self.draw_params.depth
+            },
+            .. self.draw_params.clone()
         };
-        for mesh in meshes {
-            mesh.draw(self.ctx.facade(), &mut frame, &self.shader, &uniforms, &self.draw_params)?;
+        for mesh in opaque.iter().cloned().chain(translucent.iter()) {
+            let layer = mesh.layer();
+            let uniforms = MeshUniform {
+                world: world.into(),
+                center: center.into(),
+                textures: self.sampled_textures,
+                light_map: self.sampled_light_map,
+                layer: layer.priority(),
+                glow,
+            };
+            if layer == RenderLayer::Translucent {
+                mesh.draw(self.ctx.facade(), surface, &self.shader, &uniforms, &translucent_params)?;
+            } else {
+                mesh.draw(self.ctx.facade(), surface, &self.shader, &uniforms, &self.draw_params)?;
+            }
         }
-        frame.finish().map_err(Box::new)?;
-        let raw2d: RgbaTexture2d = self.ctx.context().read_front_buffer().map_err(Box::new)?;
-        Ok(raw2d.inner())
+        Ok(())
+    }
+
+    pub fn draw_camera<'b, I>(&mut self, meshes: I, camera: &Camera) -> GEResult<RgbaImage>
+    where
+        I: Iterator<Item=&'b Mesh<MeshVertex>> 
+    {
+        self.draw(meshes, camera.matrix(), camera.center())
     }
 
 }
 
 
+const MESH_VERTICES: usize = 1 << 16;      // `Mesh` indices are u16
+
 /**
- * 
+ *  the quads of every translucent mesh in `meshes`, back to front across all of them,
+ *  regrouped into meshes drawn in order
+ */
+pub fn sort_translucent(meshes: &[&Mesh<MeshVertex>], world: Matrix4<f32>, center: Vector3<i32>) -> Vec<Mesh<MeshVertex>> {
+    // nearer quads have greater depth, so ascending depth is back to front
+    let depth = |quad: &[MeshVertex]| -> f32 {
+        let sum = quad.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, v| sum + Vector3::from(v.pos) / 16.0 + Vector3::from(v.loc).cast::<f32>().unwrap());
+        let p = world * (sum / quad.len() as f32 - center.cast::<f32>().unwrap()).extend(1.0);
+        p.z / p.w
+    };
+    let mut quads: Vec<(f32, &[MeshVertex])> = meshes.iter()
+        .filter(|mesh| mesh.layer() == RenderLayer::Translucent)
+        .flat_map(|mesh| mesh.quads())
+        .map(|quad| (depth(quad), quad))
+        .collect();
+    quads.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    quads.chunks(MESH_VERTICES / 4).map(|chunk| {
+        let mut mesh = Mesh::with_count(chunk.len());
+        for (_, quad) in chunk {
+            mesh.append(quad);
+        }
+        mesh.set_layer(RenderLayer::Translucent);
+        mesh
+    }).collect()
+}
+
+/**
+ *  meshes by render priority, a new one whenever the last of a priority is full
  */
 
 pub struct MeshGenerator {
@@ -135,7 +269,10 @@ impl MeshGenerator {
 
     pub fn unwrap(mut self) -> Vec<Mesh<MeshVertex>> {
         self.meshes.sort_by_key(|t| t.1);
-        self.meshes.into_iter().map(|t| t.0).collect()
+        self.meshes.into_iter().map(|(mut mesh, prior)| {
+            mesh.set_layer(RenderLayer::from_priority(prior));
+            mesh
+        }).collect()
     }
 }
 
@@ -146,7 +283,8 @@ impl BlockRenderer for MeshGenerator {
     fn state(&mut self, prior: i32) -> i32 {
         let old = self.meshes[self.current].1;
         if old != prior {
-            for (i, (_, id)) in self.meshes.iter().enumerate() {
+            // the last one, earlier meshes of `prior` are full
+            for (i, (_, id)) in self.meshes.iter().enumerate().rev() {
                 if *id == prior {
                     self.current = i;
                     return old;
@@ -170,6 +308,11 @@ impl BlockRenderer for MeshGenerator {
         light: u8
     ) -> Result<(), Self::E> 
     {
+        if self.meshes[self.current].0.size().0 + 4 > MESH_VERTICES {
+            let prior = self.meshes[self.current].1;
+            self.meshes.push((Mesh::new(), prior));
+            self.current = self.meshes.len() - 1;
+        }
         let mesh = &mut self.meshes[self.current].0;
         let vertexs = [
             MeshVertex { loc: loc.into(), pos: vp0.into(), tex: vt0.into(), tex_id: tex.0, color: color, light: light as u32 },
@@ -6,9 +6,25 @@ in vec2 v_light;
 
 uniform sampler2DArray textures;
 uniform sampler2D light_map;
+uniform int layer;      // 0: solid, 1: cutout mipped, 2: cutout, 3: translucent
+uniform int glow;       // block light the glow pass starts at, 0 for the colour pass
 
 out vec4 fragColor;
 
 void main() {
-    fragColor = texture(textures, v_tex) * v_color * texture(light_map, v_light);
+    vec4 color = texture(textures, v_tex);
+    if (layer == 1 || layer == 2) {
+        if (color.a < 0.5) {
+            discard;
+        }
+        color.a = 1.0;
+    }
+    if (glow > 0) {
+        // what glows is lit by blocks only, nearer blocks keep it hidden in black
+        float block_light = floor(v_light.x * 16.0);
+        float k = smoothstep(float(glow) - 1.0, 15.0, block_light);
+        fragColor = vec4(color.rgb * v_color.rgb * k, color.a * v_color.a);
+    } else {
+        fragColor = color * v_color * texture(light_map, v_light);
+    }
 }
\ No newline at end of file
@@ -1,5 +1,8 @@
 
 
+use std::io;
+use std::io::Read;
+use std::io::Write;
 use std::borrow::Cow;
 use std::collections::hash_map::HashMap;
 
@@ -16,8 +19,14 @@ use glium::uniforms::MagnifySamplerFilter;
 
 use image::RgbaImage;
 
+use log::warn;
+
 use crate::assets::util::Provider;
 use crate::model::model::TextureGen;
+use crate::model::model::WHITE_TEXTURE;
+use crate::model::model::RenderLayer;
+use crate::model::cache::Binary;
+use super::LOG_TARGET;
 
 pub type GEResult<T> = Result<T, Box<dyn std::error::Error>>;
 
@@ -25,9 +34,123 @@ pub type GEResult<T> = Result<T, Box<dyn std::error::Error>>;
 #[derive(Clone, Debug)]
 pub struct CombinedTexture(pub i32);
 
+impl Binary for CombinedTexture {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.0.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(CombinedTexture(i32::read_from(r)?))
+    }
+}
+
+
+/**
+ *  a bigger texture in the model cache means it is corrupt
+ */
+const MAX_CACHED_SIZE: u32 = 4096;
+
+/**
+ *  every texture a `CombinedTextureGen` handed out, image `i` is texture id `i + 1`; packed
+ *  into a `Texture2dArray` by `build`, and kept as it is by the model cache
+ */
+#[derive(Clone, Debug, Default)]
+pub struct TextureSet {
+
+    pub names: Vec<String>,
+
+    pub images: Vec<RgbaImage>,
+
+}
+
+impl TextureSet {
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /**
+     *  the id of texture `name`, if it was loaded
+     */
+    pub fn id(&self, name: &str) -> Option<CombinedTexture> {
+        self.names.iter().position(|n| n == name).map(|i| CombinedTexture(i as i32 + 1))
+    }
+
+    /**
+     *  layer 0 is `missing_texture`, every image is scaled to `width` x `height`
+     */
+    pub fn build<F: Facade>(&self, facade: &F, width: u32, height: u32, mipmaps: MipmapsOption) -> GEResult<Texture2dArray> {
+        let sz = self.len() as u32 + 1;
+        let tex2d_arr = Texture2dArray::empty_with_mipmaps(facade, mipmaps, width, height, sz).map_err(Box::new)?;
+        let missing = Texture2d::new(facade, RgbaTexture2d(missing_texture(width, height))).map_err(Box::new)?;
+        let fb = SimpleFrameBuffer::new(facade, tex2d_arr.layer(0).unwrap().main_level()).map_err(Box::new)?;
+        missing.as_surface().fill(&fb, MagnifySamplerFilter::Nearest);
+        for (i, (name, image)) in self.names.iter().zip(self.images.iter()).enumerate() {
+            let id = i as u32 + 1;
+            let tex2d = match Texture2d::new(facade, RgbaTexture2d(image.clone())) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(target: LOG_TARGET, "texture `{}`: {}", name, e);
+                    continue;
+                }
+            };
+            let fb = match SimpleFrameBuffer::new(facade, tex2d_arr.layer(id).unwrap().main_level()) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(target: LOG_TARGET, "texture layer {} (`{}`): {}", id, name, e);
+                    continue;
+                }
+            };
+            tex2d.as_surface().fill(&fb, MagnifySamplerFilter::Nearest);
+        }
+        Ok(tex2d_arr)
+    }
+}
+
+impl Binary for TextureSet {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.names.write_to(w)?;
+        (self.images.len() as u32).write_to(w)?;
+        for image in self.images.iter() {
+            image.width().write_to(w)?;
+            image.height().write_to(w)?;
+            w.write_all(image)?;
+        }
+        Ok(())
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let names = Vec::read_from(r)?;
+        let n = u32::read_from(r)? as usize;
+        let mut images = Vec::with_capacity(n.min(4096));
+        for _ in 0 .. n {
+            let width = u32::read_from(r)?;
+            let height = u32::read_from(r)?;
+            if width > MAX_CACHED_SIZE || height > MAX_CACHED_SIZE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("cached texture is {}x{}", width, height)));
+            }
+            let mut data = vec![0; width as usize * height as usize * 4];
+            r.read_exact(&mut data)?;
+            images.push(RgbaImage::from_raw(width, height, data).ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?);
+        }
+        Ok(TextureSet { names, images })
+    }
+}
+
+
 pub struct CombinedTextureGen<'a, F: Facade, P: Provider<Item=RgbaImage>> {
 
-    cache: HashMap<String, (CombinedTexture, Texture2d)>,
+    cache: HashMap<String, CombinedTexture>,
+
+    set: TextureSet,
+
+    info: Vec<(OpacityMask, RenderLayer)>,  // by texture id - 1
 
     provider: P,
 
@@ -41,24 +164,22 @@ impl<'a, F: Facade, P: Provider<Item=RgbaImage>> TextureGen for CombinedTextureG
         use std::collections::hash_map::Entry;
         let len = self.cache.len();
         match self.cache.entry(name.to_string()) {
-            Entry::Occupied(entry) => entry.into_mut().0.clone(),
+            Entry::Occupied(entry) => entry.get().clone(),
             Entry::Vacant(entry) => {
-                match self.provider.provide(name) {
+                let image = match self.provider.provide(name) {
+                    None if name == WHITE_TEXTURE => Some(RgbaImage::from_pixel(16, 16, image::Rgba([255; 4]))),
+                    image => image,
+                };
+                match image {
                     Some(image) => {
                         let id = len as i32 + 1;
-                        match Texture2d::new(self.facade, RgbaTexture2d(image)) {
-                            Ok(tex2d) => { 
-                                let tex = CombinedTexture(id);
-                                entry.insert((tex, tex2d)).0.clone()
-                            },
-                            Err(e) => {
-                                eprintln!("@[{}] {}", name, e); //TODO: log
-                                CombinedTexture(0)
-                            }
-                        }
+                        self.info.push((OpacityMask::from_image(&image), texture_layer(name, &image)));
+                        self.set.names.push(name.to_string());
+                        self.set.images.push(image);
+                        entry.insert(CombinedTexture(id)).clone()
                     },
                     None => {
-                        eprintln!("texture not found: {}", name); //TODO: log
+                        warn!(target: LOG_TARGET, "texture not found: {}", name);
                         CombinedTexture(0)
                     }
                 }         
@@ -66,6 +187,20 @@ impl<'a, F: Facade, P: Provider<Item=RgbaImage>> TextureGen for CombinedTextureG
         }
     }
 
+    fn is_opaque(&self, tex: &Self::Texture, uv: [f32; 4]) -> bool {
+        match self.info(tex) {
+            Some((mask, _)) => mask.is_opaque(uv),
+            None => true,
+        }
+    }
+
+    fn render_layer(&self, tex: &Self::Texture) -> RenderLayer {
+        match self.info(tex) {
+            Some((_, layer)) => *layer,
+            None => RenderLayer::Solid,
+        }
+    }
+
 }
 
 impl<'a, F: Facade, P: Provider<Item=RgbaImage>> CombinedTextureGen<'a, F, P> { 
@@ -73,31 +208,113 @@ impl<'a, F: Facade, P: Provider<Item=RgbaImage>> CombinedTextureGen<'a, F, P> {
     pub fn new(facade: &'a F, provider: P) -> Self {
         CombinedTextureGen {
             cache: HashMap::new(),
+            set: TextureSet::default(),
+            info: Vec::new(),
             provider,
             facade,
         }
     }
 
+    fn info(&self, tex: &CombinedTexture) -> Option<&(OpacityMask, RenderLayer)> {
+        if tex.0 > 0 {
+            self.info.get(tex.0 as usize - 1)
+        } else {
+            None
+        }
+    }
+
+    /**
+     *  the textures loaded so far, see `TextureSet::build`
+     */
+    pub fn into_set(self) -> TextureSet {
+        self.set
+    }
+
     pub fn build(self, width: u32, height: u32, mipmaps: MipmapsOption) -> GEResult<Texture2dArray> {
-        let sz = self.cache.len() as u32 + 1 ;
-        let tex2d_arr = Texture2dArray::empty_with_mipmaps(self.facade, mipmaps, width, height, sz).map_err(Box::new)?;
-        for (k, v) in self.cache {
-            let id = (v.0).0;
-            let tex2d = v.1;
-            let fb = match SimpleFrameBuffer::new(self.facade, tex2d_arr.layer(id as u32).unwrap().main_level()) {
-                Ok(v) => v,
-                Err(e) => {
-                    eprintln!("@ [{}] {}", id, e); //TODO log
-                    continue;
-                }
-            };
-            tex2d.as_surface().fill(&fb, MagnifySamplerFilter::Nearest);
+        self.set.build(self.facade, width, height, mipmaps)
+    }
+}
+
+
+
+/**
+ *  vanilla's magenta/black checkerboard, used for texture id 0
+ */
+pub fn missing_texture(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        if (x * 2 / width) == (y * 2 / height) {
+            image::Rgba([0xF8, 0x00, 0xF8, 0xFF])
+        } else {
+            image::Rgba([0x00, 0x00, 0x00, 0xFF])
         }
-        Ok(tex2d_arr)
+    })
+}
+
+
+/**
+ *  partially transparent texels make a texture translucent, fully transparent ones make it cutout;
+ *  leaves and the grass overlay are mipped cutout as in vanilla
+ */
+pub fn texture_layer(name: &str, image: &RgbaImage) -> RenderLayer {
+    let mut layer = RenderLayer::Solid;
+    for p in image.pixels() {
+        match p.0[3] {
+            255 => { },
+            0 => layer = RenderLayer::Cutout,
+            _ => return RenderLayer::Translucent,
+        }
+    }
+    if layer == RenderLayer::Cutout && (name.ends_with("_leaves") || name.ends_with("grass_block_side_overlay")) {
+        return RenderLayer::CutoutMipped;
     }
+    layer
 }
 
 
+/**
+ *  16x16 cells of a texture's first frame, a bit is set if every texel in the cell is opaque
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpacityMask([u16; 16]);
+
+impl OpacityMask {
+
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let w = image.width();
+        let h = std::cmp::min(image.width(), image.height());     // animated textures are vertical strips
+        let span = |i: u32, n: u32| {
+            let start = i * n / 16;
+            start .. std::cmp::max(start + 1, (i + 1) * n / 16)
+        };
+        let mut rows = [0u16; 16];
+        if w == 0 || h == 0 {
+            return OpacityMask(rows);
+        }
+        for (cy, row) in rows.iter_mut().enumerate() {
+            for cx in 0 .. 16 {
+                let opaque = span(cy as u32, h).all(|y| span(cx, w).all(|x| image.get_pixel(x, y).0[3] == 255));
+                if opaque {
+                    *row |= 1 << cx;
+                }
+            }
+        }
+        OpacityMask(rows)
+    }
+
+    /**
+     *  `uv` is `[u0, v0, u1, v1]` in 0 - 16, either corner may come first
+     */
+    pub fn is_opaque(&self, uv: [f32; 4]) -> bool {
+        let range = |a: f32, b: f32| {
+            let lo = a.min(b).floor().max(0.0) as usize;
+            let hi = a.max(b).ceil().min(16.0) as usize;
+            lo .. std::cmp::max(hi, lo + 1).min(16)
+        };
+        let cols = range(uv[0], uv[2]);
+        range(uv[1], uv[3]).all(|y| cols.clone().all(|x| self.0[y] & (1 << x) != 0))
+    }
+}
+
 
 pub struct RgbaTexture2d(RgbaImage);
 
@@ -1,6 +1,7 @@
 pub mod assets;
 pub mod model;
 pub mod glrender;
+pub mod anvil;
 
 #[cfg(test)]
 mod tests {
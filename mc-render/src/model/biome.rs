@@ -68,6 +68,20 @@ impl BiomeColor {
         }
     }
 
+    /**
+     *  look up a biome by its resource name, e.g. `minecraft:dark_forest` -> `Dark Forest`
+     */
+    pub fn find(&self, name: &str) -> Option<Biome> {
+        let name = name.rsplit(':').next().unwrap_or(name);
+        let matches = |n: &str| {
+            n.len() == name.len() && n.bytes().zip(name.bytes()).all(|(a, b)| {
+                let a = if a == b' ' { b'_' } else { a.to_ascii_lowercase() };
+                a == b
+            })
+        };
+        self.biomes.iter().position(|(n, _)| matches(n.as_str())).map(Biome)
+    }
+
     #[inline]
     fn get<'a, T>(vec: &'a Vec<T>, biome: &Biome) -> &'a T {
         if biome.0 < vec.len() {
@@ -3,11 +3,92 @@ use std::slice::Iter as SliceIter;
 use cgmath::Vector3;
 
 use crate::assets::data_type::Face;
+use super::fluid::Fluid;
+
+
+/**
+ *  vertical extent of a world: `min` is the lowest block layer, `max` is one above the highest
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeightRange {
+
+    pub min: i32,
+
+    pub max: i32,
+
+}
+
+impl HeightRange {
+
+    pub const LEGACY: HeightRange = HeightRange { min: 0, max: 256 };       // before 1.18
+
+    pub const EXTENDED: HeightRange = HeightRange { min: -64, max: 320 };   // 1.18 and later
+
+    pub fn new(min: i32, max: i32) -> Self {
+        HeightRange { min, max }
+    }
+
+    pub fn contains(&self, y: i32) -> bool {
+        y >= self.min && y < self.max
+    }
+
+    pub fn span(&self) -> i32 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> i32 {
+        self.min + self.span() / 2
+    }
+}
+
+impl Default for HeightRange {
+
+    fn default() -> Self {
+        HeightRange::LEGACY
+    }
+}
+
+
+/**
+ *  axis aligned box of blocks, `min` inclusive and `max` exclusive
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockBounds {
+
+    pub min: Vector3<i32>,
+
+    pub max: Vector3<i32>,
+
+}
+
+impl BlockBounds {
+
+    pub fn new(min: Vector3<i32>, max: Vector3<i32>) -> Self {
+        BlockBounds { min, max }
+    }
+
+    /**
+     *  `width` x `depth` columns starting at (`x`, `z`) over the whole `height`
+     */
+    pub fn columns(x: i32, z: i32, width: i32, depth: i32, height: &HeightRange) -> Self {
+        BlockBounds::new(Vector3::new(x, height.min, z), Vector3::new(x + width, height.max, z + depth))
+    }
+
+    pub fn contains(&self, loc: &Vector3<i32>) -> bool {
+        loc.x >= self.min.x && loc.x < self.max.x
+            && loc.y >= self.min.y && loc.y < self.max.y
+            && loc.z >= self.min.z && loc.z < self.max.z
+    }
+
+    pub fn center(&self) -> Vector3<i32> {
+        self.min + (self.max - self.min) / 2
+    }
+}
 
 
 impl Face {
 
-    pub fn near(&self, loc: &Vector3<i32>) -> Option<Vector3<i32>> {
+    pub fn near(&self, loc: &Vector3<i32>, height: &HeightRange) -> Option<Vector3<i32>> {
         match self {
             Face::West => {
                 if loc.x > std::i32::MIN {
@@ -17,7 +98,7 @@ impl Face {
                 }
             },
             Face::Down => {
-                if loc.y > 0 {
+                if loc.y > height.min {
                     Some(Vector3::new(loc.x, loc.y - 1, loc.z))
                 } else {
                     None
@@ -38,7 +119,7 @@ impl Face {
                 }
             },
             Face::Up => {
-                if loc.y < 255 {
+                if loc.y < height.max - 1 {
                     Some(Vector3::new(loc.x, loc.y + 1, loc.z))
                 } else {
                     None
@@ -76,6 +157,57 @@ pub trait RenderableBlock<'a> {
     }
 
     fn get_light(&self) -> u8;
+
+    /**
+     *  block light the block gives off itself, 0 to 15
+     */
+    fn get_emission(&self) -> u8 {
+        0
+    }
+
+    /**
+     *  `get_light` with the block light of emissive blocks raised to 15, so they stay at full brightness
+     *  whatever the sky light is
+     */
+    fn get_render_light(&self) -> u8 {
+        let light = self.get_light();
+        if self.get_emission() > 0 {
+            light | 0x0F
+        } else {
+            light
+        }
+    }
+
+    /**
+     *  level of the fluid in the block, 0 for a source; a full source for water and waterlogged blocks by default
+     */
+    fn get_fluid_level(&self) -> Option<u8> {
+        if self.is_water() || self.is_water_logged() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn is_snowy(&self) -> bool {
+        false
+    }
+
+    /**
+     *  the fluid `fluid::draw` meshes for this block; `draw` then leaves out the water models, and the
+     *  block models of lava. none by default, water is drawn with `get_water_models`
+     */
+    fn get_fluid(&self) -> Option<Fluid> {
+        None
+    }
+
+    /**
+     *  whether this block hides the face of a neighbour touching its `face`; any non-air block by default
+     */
+    fn occludes(&self, face: &Face) -> bool {
+        let _ = face;
+        !self.is_air()
+    }
     
 }
 
@@ -84,6 +216,10 @@ pub trait World<'a> {
     type Block: RenderableBlock<'a>;
 
     fn get(&'a self, loc: &Vector3<i32>) -> Self::Block;
+
+    fn height_range(&self) -> HeightRange {
+        HeightRange::LEGACY
+    }
     
     fn is_air(&'a self, loc: &Vector3<i32>) -> bool {
         self.get(loc).is_air()
@@ -97,6 +233,10 @@ pub trait World<'a> {
         self.get(loc).is_water_logged()
     }
 
+    fn occludes(&'a self, loc: &Vector3<i32>, face: &Face) -> bool {
+        self.get(loc).occludes(face)
+    }
+
 }
 
 
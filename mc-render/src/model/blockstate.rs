@@ -1,260 +1,610 @@
-use std::collections::btree_map::BTreeMap as Map;
-use std::collections::btree_map::Values;
-use std::collections::btree_map::ValuesMut;
-use std::borrow::Borrow;
+use std::fmt;
+use std::collections::btree_map::BTreeMap;
+
+use cgmath::Vector3;
+
+use crate::assets::data_type::Face;
+use crate::assets::data_type::Axis;
+use crate::assets::data_raw::CaseNode;
+use crate::assets::data_raw::BlockReportRaw;
+use super::random::JavaRandom;
+use super::random::position_random;
 
 /**
- * 
+ *  one property test, `facing=north`; several values (`side|up`) match any of them
  */
-#[derive(Clone, Debug)]
-pub struct Expression<K, V, M> {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Term {
 
-    keys: Map<K, M>,
+    pub property: String,
 
-    values: Map<M, V>,
+    pub values: Vec<String>,
 
-    mask: M,
 }
 
-impl<K: std::cmp::Ord, V> Default for Expression<K, V, usize> {
+impl Term {
+
+    /**
+     *  `property=value`; without `=` the whole string is the property and the value is empty
+     */
+    pub fn parse(s: &str) -> Self {
+        let mut it = s.splitn(2, '=');
+        let property = it.next().unwrap_or_default().to_string();
+        let value = it.next().unwrap_or_default();
+        Term {
+            property,
+            values: value.split('|').map(String::from).collect(),
+        }
+    }
 
-    fn default() -> Self {
-        Expression {
-            keys: Map::default(),
-            values: Map::default(),
-            mask: 0,
+    fn from_node(node: &CaseNode<String>) -> Self {
+        match node {
+            CaseNode::Item(s) => Term::parse(s),
+            CaseNode::Array(list, _) => {
+                let mut terms = list.iter().map(|s| Term::parse(s));
+                let mut term = terms.next().unwrap_or_else(|| Term::parse(""));
+                for t in terms {
+                    term.values.extend(t.values);
+                }
+                term
+            },
+        }
+    }
+
+    /**
+     *  the value of `property` in `state` if it is not one of `values`
+     */
+    fn check<'a>(&self, state: &State<'a>) -> Result<(), Option<&'a str>> {
+        match state.get(&self.property) {
+            Some(value) if self.values.iter().any(|v| v == value) => Ok(()),
+            found => Err(found),
         }
     }
 }
 
-impl<K: std::cmp::Ord + Clone, V> Expression<K, V, usize> {
+impl fmt::Display for Term {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.property, self.values.join("|"))
+    }
+}
 
-    pub fn insert<'a, I:Iterator<Item=&'a K>>(&'a mut self, key: I, value: V) -> Option<usize> {
-        let mut m = self.get_initial_link();
-        for k in key {
-            self.insert_key(k, &mut m)?;
+
+/**
+ *  when a variant or multipart case applies: any one of the `OR` alternatives, each a list of
+ *  terms that all have to hold. there is no limit on properties or values
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+
+    any: Vec<Vec<Term>>,
+
+}
+
+impl Condition {
+
+    /**
+     *  holds for every state, a variant `""` or a multipart case without `when`
+     */
+    pub fn always() -> Self {
+        Condition {
+            any: vec![Vec::new()],
         }
-        self.values.insert(m, value);
-        Some(m)
     }
 
-    pub fn insert_key(&mut self, k: &K, m: &mut usize) -> Option<usize> {
-        if let Some(n) = self.keys.get(k) {
-            *m |= *n;
-            Some(*n)
-        } else {
-            if self.mask == std::usize::MAX {
-                return None;
-            }
-            let n = self.mask + 1;
-            self.mask = n | self.mask;
-            self.keys.insert(k.clone(), n);
-            *m |= n;
-            Some(n)
+    /**
+     *  a variant key split at `,`
+     */
+    pub fn from_keys<S: AsRef<str>>(keys: &[S]) -> Self {
+        Condition {
+            any: vec![keys.iter().map(|k| Term::parse(k.as_ref())).collect()],
         }
     }
 
-    pub fn insert_value_unchecked(&mut self, m: usize, v: V) -> Option<V> {
-        self.values.insert(m, v)
+    /**
+     *  a multipart `when`, `OR` lists and `a|b` values are kept as they are
+     */
+    pub fn from_case(case: &CaseNode<Vec<CaseNode<String>>>) -> Self {
+        let all = |list: &[CaseNode<String>]| list.iter().map(Term::from_node).collect();
+        match case {
+            CaseNode::Item(list) => Condition { any: vec![all(list)] },
+            CaseNode::Array(alternatives, _) => Condition { any: alternatives.iter().map(|list| all(list)).collect() },
+        }
     }
-}
 
-impl<K: std::cmp::Ord, V> Expression<K, V, usize> {
+    /**
+     *  `OR` alternatives as they are kept, see `alternatives`
+     */
+    pub fn from_alternatives(any: Vec<Vec<Term>>) -> Self {
+        Condition { any }
+    }
+
+    pub fn alternatives(&self) -> &[Vec<Term>] {
+        &self.any
+    }
 
-    pub fn get_initial_link(&self) -> usize {
-        0
+    pub fn is_always(&self) -> bool {
+        self.any.iter().any(Vec::is_empty)
     }
 
-    pub fn size(&self) -> (usize, usize) {
-        (self.keys.len(), self.values.len())
+    /**
+     *  terms of the most specific alternative that holds in `state`
+     */
+    fn matching(&self, state: &State) -> Option<usize> {
+        self.any.iter()
+            .filter(|terms| terms.iter().all(|term| term.check(state).is_ok()))
+            .map(Vec::len)
+            .max()
     }
 
-    pub fn get<'a, Q: 'a, I>(&'a self, key: I, strict: bool) -> Option<&'a V> 
-    where
-        Q: std::cmp::Ord,
-        K: Borrow<Q>,
-        I: Iterator<Item = &'a Q>,
-    {
-        if !strict && self.keys.len() == 0 {
-            return self.values.get(&0);
+    pub fn matches(&self, state: &State) -> bool {
+        self.matching(state).is_some()
+    }
+
+    /**
+     *  why `state` fails, the first broken term of every alternative; empty if it matches
+     */
+    pub fn mismatch(&self, state: &State) -> Vec<(Term, Option<String>)> {
+        if self.matches(state) {
+            return Vec::new();
         }
-        let mut m = 0;
-        let keys = &self.keys;
-        for k in key {
-            match keys.get(k) {
-                Some(n) => {
-                    m |= n;
-                },
-                None => {
-                    if strict { return None; }
+        self.any.iter()
+            .filter_map(|terms| terms.iter().find_map(|term| term.check(state).err().map(|found| (term.clone(), found.map(String::from)))))
+            .collect()
+    }
+}
+
+impl fmt::Display for Condition {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, terms) in self.any.iter().enumerate() {
+            if i > 0 {
+                write!(f, " OR ")?;
+            }
+            for (j, term) in terms.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ",")?;
                 }
+                write!(f, "{}", term)?;
             }
         }
-        self.values.get(&m)
+        Ok(())
     }
+}
+
 
-    pub fn get_mut<'a, Q: 'a, I>(&'a mut self, key: I, strict: bool) -> Option<&'a mut V> 
-    where
-        Q: std::cmp::Ord,
-        K: Borrow<Q>,
-        I: Iterator<Item = &'a Q>,
-    {
-        if !strict && self.keys.len() == 0 {
-            return self.values.get_mut(&0);
+/**
+ *  property values of one blockstate, from `property=value` strings
+ */
+#[derive(Clone, Debug, Default)]
+pub struct State<'a>(Vec<(&'a str, &'a str)>);
+
+impl<'a> State<'a> {
+
+    pub fn get(&self, property: &str) -> Option<&'a str> {
+        self.0.iter().find(|(k, _)| *k == property).map(|(_, v)| *v)
+    }
+    /**
+     *  adds the default value of every property of `properties` the state leaves out
+     */
+    pub fn complete(&mut self, properties: &'a BlockProperties) {
+        for (property, value) in properties.default.iter() {
+            if self.get(property).is_none() {
+                self.0.push((property.as_str(), value.as_str()));
+            }
         }
-        let mut m = 0;
-        let keys = &self.keys;
-        for k in key {
-            match keys.get(k) {
-                Some(n) => {
-                    m |= n;
-                },
-                None => {
-                    if strict { return None; }
-                }
+    }
+
+    /**
+     *  every property has to be one of `properties` and hold one of its values
+     */
+    pub fn check(&self, properties: &BlockProperties) -> Result<(), MatchError> {
+        for (property, value) in self.0.iter() {
+            match properties.values.get(*property) {
+                Some(values) if values.iter().any(|v| v == value) => { },
+                Some(values) => return Err(MatchError::InvalidValue {
+                    property: property.to_string(),
+                    value: value.to_string(),
+                    allowed: values.clone(),
+                }),
+                None => return Err(MatchError::UnknownProperty(property.to_string())),
             }
         }
-        self.values.get_mut(&m)
+        Ok(())
     }
+}
+
+impl<'a, I: Iterator<Item = &'a str>> From<I> for State<'a> {
 
-    pub fn all<'a>(&'a self) -> Values<'a, usize, V> {
-        self.values.values()
+    fn from(key: I) -> Self {
+        State(key.filter_map(|s| {
+            let mut it = s.splitn(2, '=');
+            Some((it.next()?, it.next()?))
+        }).collect())
     }
+}
 
-    pub fn all_mut<'a>(&'a mut self) -> ValuesMut<'a, usize, V> {
-        self.values.values_mut()
+
+/**
+ *  a block name with its properties, parsed once, e.g. from a voxelmap key line or an anvil palette entry
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockStateKey {
+
+    pub name: String,
+
+    pub properties: BTreeMap<String, String>,
+
+}
+
+impl BlockStateKey {
+
+    /**
+     *  `key` as `property=value` strings; anything without a `=` is left out
+     */
+    pub fn new<'a, I: Iterator<Item = &'a str>>(name: &str, key: I) -> Self {
+        let properties = key.filter_map(|s| {
+            let mut it = s.splitn(2, '=');
+            Some((it.next()?.to_string(), it.next()?.to_string()))
+        }).collect();
+        BlockStateKey {
+            name: name.to_string(),
+            properties,
+        }
+    }
+
+    pub fn get(&self, property: &str) -> Option<&str> {
+        self.properties.get(property).map(String::as_str)
+    }
+
+    /**
+     *  the properties as `property=value`, sorted by property, for `BlockState::get`
+     */
+    pub fn key_iter(&self) -> impl Iterator<Item = String> + '_ {
+        self.properties.iter().map(|(k, v)| format!("{}={}", k, v))
+    }
+
+    /**
+     *  a `true`/`false` property, `false` if it is missing
+     */
+    pub fn flag(&self, property: &str) -> bool {
+        self.get(property) == Some("true")
+    }
+
+    /**
+     *  a numeric property such as `age` or `power`
+     */
+    pub fn number(&self, property: &str) -> Option<u8> {
+        self.get(property).and_then(|v| v.parse().ok())
+    }
+
+    pub fn waterlogged(&self) -> bool {
+        self.flag("waterlogged")
+    }
+
+    pub fn lit(&self) -> bool {
+        self.flag("lit")
+    }
+
+    pub fn snowy(&self) -> bool {
+        self.flag("snowy")
+    }
+
+    pub fn facing(&self) -> Option<Face> {
+        self.get("facing").and_then(Face::from_name)
+    }
+
+    pub fn axis(&self) -> Option<Axis> {
+        self.get("axis").and_then(Axis::from_name)
+    }
+
+    pub fn half(&self) -> Option<Half> {
+        self.get("half").and_then(Half::from_name)
+    }
+
+    /**
+     *  redstone signal strength, 0 to 15
+     */
+    pub fn power(&self) -> Option<u8> {
+        self.number("power")
+    }
+
+    pub fn age(&self) -> Option<u8> {
+        self.number("age")
+    }
+
+    /**
+     *  fluid level (0 is a source, 8 and more falling) or the fill level of cauldrons and composters
+     */
+    pub fn level(&self) -> Option<u8> {
+        self.number("level")
     }
+}
+
+impl fmt::Display for BlockStateKey {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.properties.is_empty() {
+            let key: Vec<String> = self.key_iter().collect();
+            write!(f, "[{}]", key.join(","))?;
+        }
+        Ok(())
+    }
+}
+
 
-    pub fn transf_into<K2, V2, FK, FV, E>(self, mut fk: FK, mut fv: FV) -> Result<Expression<K2, V2, usize>, E> 
-    where
-        K2: std::cmp::Ord,
-        FK: FnMut(K)->Result<K2, E>, 
-        FV: FnMut(V)->Result<V2, E>,
-    {
-        let mut keys = Map::new();
-        for (k, m) in self.keys {
-            let k2 = fk(k)?;
-            keys.insert(k2, m);
+/**
+ *  the `half` property: `Top`/`Bottom` of slabs, stairs and trapdoors, `Upper`/`Lower` of doors and tall plants
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Half {
+    Top,
+    Bottom,
+    Upper,
+    Lower,
+}
+
+impl Half {
+
+    pub fn from_name(name: &str) -> Option<Half> {
+        match name {
+            "top" => Some(Half::Top),
+            "bottom" => Some(Half::Bottom),
+            "upper" => Some(Half::Upper),
+            "lower" => Some(Half::Lower),
+            _ => None,
         }
-        let mut values = Map::new();
-        for (m, v) in self.values {
-            let v2 = fv(v)?;
-            values.insert(m, v2);
+    }
+}
+
+
+/**
+ *  a variant or multipart case that does not apply to some state, see `BlockState::mismatches`
+ */
+#[derive(Clone, Debug)]
+pub struct Mismatch {
+
+    pub condition: Condition,
+
+    /**
+     *  the first failing term of every `OR` alternative, with the value the state has
+     */
+    pub failed: Vec<(Term, Option<String>)>,
+
+}
+
+impl fmt::Display for Mismatch {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`:", self.condition)?;
+        for (term, found) in self.failed.iter() {
+            match found {
+                Some(value) => write!(f, " {} is {}", term, value)?,
+                None => write!(f, " {} is missing", term)?,
+            }
         }
-        Ok(Expression {
-            keys,
-            values,
-            mask: self.mask
-        })
+        Ok(())
     }
 }
 
 
+/**
+ *  the properties of one block with their possible values, and the default state
+ */
+#[derive(Clone, Debug, Default)]
+pub struct BlockProperties {
+
+    pub values: BTreeMap<String, Vec<String>>,
+
+    pub default: BTreeMap<String, String>,
+
+}
+
+impl BlockProperties {
+
+    /**
+     *  the state marked `default`, or the first one
+     */
+    pub fn from_raw(raw: &BlockReportRaw) -> Self {
+        let default = raw.states.iter()
+            .find(|state| state.default)
+            .or_else(|| raw.states.first())
+            .map(|state| state.properties.clone())
+            .unwrap_or_default();
+        BlockProperties {
+            values: raw.properties.clone(),
+            default,
+        }
+    }
+}
+
+
+/**
+ *  `Lenient` ignores properties and values it does not know, `Strict` fails on them
+ *  (only with `BlockProperties` to check against)
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+
+/**
+ *  why `BlockState::resolve` found no model
+ */
+#[derive(Clone, Debug)]
+pub enum MatchError {
+    UnknownProperty(String),
+    InvalidValue { property: String, value: String, allowed: Vec<String> },
+    NoMatch(Vec<Mismatch>),
+}
+
+impl fmt::Display for MatchError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownProperty(property) => write!(f, "unknown property `{}`", property),
+            Self::InvalidValue { property, value, allowed } => write!(f, "`{}={}` is not one of {}", property, value, allowed.join("|")),
+            Self::NoMatch(mismatches) => {
+                write!(f, "no variant matches")?;
+                for mismatch in mismatches {
+                    write!(f, "; {}", mismatch)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for MatchError {
+
+}
+
+
 /**
  * 
  */
 
 #[derive(Clone, Debug)]
-pub enum BlockState<K, M> {
+pub enum BlockState<M> {
     Single(M),
-    Variants(Expression<K, M, usize>),
-    MultiPart(Expression<K, M, usize>, Vec<usize>),
+    Variants(Vec<(Condition, M)>),
+    MultiPart(Vec<(Condition, M)>),
 }
 
-impl<K: std::cmp::Ord, M: Clone> BlockState<K, M> {
+impl<M: Clone> BlockState<M> {
+
+    /**
+     *  the most specific variant that matches, or every multipart case that does; properties
+     *  `key` leaves out fail the cases that test them, see `resolve`
+     */
+    pub fn get<'a, I: Iterator<Item = &'a str>>(&self, key: I) -> Vec<M> {
+        self.get_state(&State::from(key))
+    }
+
+    /**
+     *  `get` the vanilla way: `key` is completed with the defaults of `properties`, and checked
+     *  against them in `MatchMode::Strict`. a variant block without a matching variant is an
+     *  error, a multipart block may have nothing to draw
+     */
+    pub fn resolve<'a, I: Iterator<Item = &'a str>>(&self, key: I, properties: Option<&'a BlockProperties>, mode: MatchMode) -> Result<Vec<M>, MatchError> {
+        let mut state = State::from(key);
+        if let Some(properties) = properties {
+            if mode == MatchMode::Strict {
+                state.check(properties)?;
+            }
+            state.complete(properties);
+        }
+        let models = self.get_state(&state);
+        if models.is_empty() {
+            if let Self::Variants(_) = self {
+                return Err(MatchError::NoMatch(self.mismatches_state(&state)));
+            }
+        }
+        Ok(models)
+    }
 
-    pub fn get<'a, Q: 'a + ?Sized, I>(&'a self, key: I) -> Vec<M> 
-    where
-        Q: std::cmp::Ord,
-        K: Borrow<Q>,
-        I: Iterator<Item = &'a Q>,
-    {
+    fn get_state(&self, state: &State) -> Vec<M> {
         match self {
             Self::Single(model) => {
                 vec![model.clone()]
             }
-            Self::Variants(expr) => {
-                let mut m = 0;
-                for k in key {
-                    if let Some(n) = expr.keys.get(k) {
-                        m |= n;
+            Self::Variants(variants) => {
+                let mut best: Option<(usize, &M)> = None;
+                for (condition, model) in variants.iter() {
+                    match (condition.matching(state), best) {
+                        (Some(n), Some((m, _))) if n <= m => { },
+                        (Some(n), _) => best = Some((n, model)),
+                        (None, _) => { },
                     }
                 }
-                if let Some(model) = expr.values.get(&m) {
-                    vec![model.clone()]
-                } else {
-                    Vec::new()
-                }
+                best.map(|(_, model)| model.clone()).into_iter().collect()
             }
-            Self::MultiPart(expr, group) => {
-                let mut m = 0;
-                for k in key {
-                    if let Some(n) = expr.keys.get(k) {
-                        m |= n;
-                    }
-                }
-                let mut parts = Vec::with_capacity(group.len());
-                for mask in group.iter() {
-                    let n = *mask & m;
-                    if let Some(model) = expr.values.get(&n) {
-                        parts.push(model.clone())
-                    }
-                }
-                parts
+            Self::MultiPart(parts) => {
+                parts.iter()
+                    .filter(|(condition, _)| condition.matches(state))
+                    .map(|(_, model)| model.clone())
+                    .collect()
             }
         }
-        
     }
-} 
-
-impl<K: std::cmp::Ord + Clone, M> BlockState<K, M> {
 
-    pub fn start_group(&mut self) {
+    /**
+     *  models of the default state of the block, for previews when no blockstate is given: the
+     *  defaults of `properties`, or without them the first variant, or the state the first terms
+     *  of the first multipart case ask for. every multipart case that holds there is drawn
+     */
+    pub fn any(&self, properties: Option<&BlockProperties>) -> Vec<M> {
+        if let Some(properties) = properties {
+            let mut state = State::default();
+            state.complete(properties);
+            let models = self.get_state(&state);
+            if !models.is_empty() {
+                return models;
+            }
+        }
         match self {
-            Self::MultiPart(expr, group) => {
-                group.push(expr.get_initial_link());
-            },
-            _ => {
-                panic!("invalid operation")
+            Self::Single(model) => vec![model.clone()],
+            Self::Variants(variants) => variants.first().map(|(_, model)| model.clone()).into_iter().collect(),
+            Self::MultiPart(parts) => {
+                let terms = parts.first()
+                    .and_then(|(condition, _)| condition.alternatives().first())
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let state = State(terms.iter()
+                    .filter_map(|term| term.values.first().map(|value| (term.property.as_str(), value.as_str())))
+                    .collect());
+                self.get_state(&state)
             }
         }
     }
+}
+
+impl<M> BlockState<M> {
 
-    pub fn insert_group<'a, I:Iterator<Item=&'a K>>(&'a mut self, key: I, value: M) -> Option<usize> {
+    /**
+     *  every model of every variant or multipart case
+     */
+    pub fn all(&self) -> Vec<&M> {
         match self {
-            Self::MultiPart(expr, group) => {
-                let mut m = expr.get_initial_link();
-                for k in key {
-                    expr.insert_key(k, &mut m)?;
-                }
-                let mask = group.last_mut()?;
-                expr.values.insert(m, value);
-                *mask |= m;
-                Some(m)
-            },
-            Self::Variants(expr) => {
-                let mut m = expr.get_initial_link();
-                for k in key {
-                    expr.insert_key(k, &mut m)?;
-                }
-                expr.values.insert(m, value);
-                Some(m)
-            }
-            Self::Single(model) => {
-                panic!("invalid operation")
-            }
+            Self::Single(model) => vec![model],
+            Self::Variants(cases) | Self::MultiPart(cases) => cases.iter().map(|(_, model)| model).collect(),
         }
     }
 
+    /**
+     *  every variant or multipart case that does not apply to the state `key`
+     */
+    pub fn mismatches<'a, I: Iterator<Item = &'a str>>(&self, key: I) -> Vec<Mismatch> {
+        self.mismatches_state(&State::from(key))
+    }
+
+    fn mismatches_state(&self, state: &State) -> Vec<Mismatch> {
+        let cases = match self {
+            Self::Single(_) => return Vec::new(),
+            Self::Variants(cases) | Self::MultiPart(cases) => cases,
+        };
+        cases.iter()
+            .map(|(condition, _)| Mismatch { condition: condition.clone(), failed: condition.mismatch(state) })
+            .filter(|mismatch| !mismatch.failed.is_empty())
+            .collect()
+    }
+
+    /**
+     *  a variant list with nothing but `""` is a single model
+     */
     pub fn try_simplify_variant(self) -> Self {
         match self {
-            Self::Variants(mut expr) => {
-                if expr.values.len() == 1 {
-                    if let Some(model) = expr.values.remove(&0) {
+            Self::Variants(mut variants) => {
+                if variants.len() == 1 && variants[0].0.is_always() {
+                    if let Some((_, model)) = variants.pop() {
                         return Self::Single(model)
                     }
                 }
-                Self::Variants(expr)
+                Self::Variants(variants)
             },
             _ => {
                 panic!("invalid operation")
@@ -263,6 +613,124 @@ impl<K: std::cmp::Ord + Clone, M> BlockState<K, M> {
     }
 }
 
+/**
+ *  a variant with every weighted alternative of its model list, picked per position the way
+ *  vanilla `WeightedBakedModel` does
+ */
+#[derive(Clone, Debug)]
+pub struct Weighted<M> {
+
+    list: Vec<(M, u32)>,
+
+    total: u32,
+
+    part: bool,     // a multipart case, see `into_part`
+
+}
+
+impl<M> Default for Weighted<M> {
+
+    fn default() -> Self {
+        Weighted {
+            list: Vec::new(),
+            total: 0,
+            part: false,
+        }
+    }
+}
+
+impl<M> From<M> for Weighted<M> {
+
+    fn from(model: M) -> Self {
+        Weighted {
+            list: vec![(model, 1)],
+            total: 1,
+            part: false,
+        }
+    }
+}
+
+impl<M> Weighted<M> {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     *  a `weight` of 0 counts as 1
+     */
+    pub fn push(&mut self, model: M, weight: u32) {
+        let weight = weight.max(1);
+        self.list.push((model, weight));
+        self.total += weight;
+    }
+
+    /**
+     *  the alternatives of a multipart case: vanilla `MultipartBakedModel` draws one `nextLong` from
+     *  the position seed and picks every case with a `Random` of that
+     */
+    pub fn into_part(mut self) -> Self {
+        self.part = true;
+        self
+    }
+
+    pub fn is_part(&self) -> bool {
+        self.part
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn first(&self) -> Option<&M> {
+        self.list.first().map(|(model, _)| model)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &M> {
+        self.list.iter().map(|(model, _)| model)
+    }
+
+    /**
+     *  alternatives with their weights, in the order of `push`
+     */
+    pub fn entries(&self) -> impl Iterator<Item = (&M, u32)> {
+        self.list.iter().map(|(model, weight)| (model, *weight))
+    }
+
+    /**
+     *  the alternative for a block seeded with `seed` (see `random::position_random`); the same
+     *  seed always gets the same one
+     */
+    pub fn pick(&self, seed: i64) -> Option<&M> {
+        if self.list.len() < 2 {
+            return self.first();
+        }
+        let seed = if self.part { JavaRandom::new(seed).next_long() } else { seed };
+        let mut r = (JavaRandom::new(seed).next_long() as i32).wrapping_abs() % self.total as i32;
+        for (model, weight) in self.list.iter() {
+            r -= *weight as i32;
+            if r < 0 {
+                return Some(model);
+            }
+        }
+        self.first()
+    }
+}
+
+impl<M: Clone> BlockState<Weighted<M>> {
+
+    /**
+     *  models of the block at `loc`, one alternative of every variant or part
+     */
+    pub fn get_at<'a, I: Iterator<Item = &'a str>>(&self, key: I, loc: &Vector3<i32>) -> Vec<M> {
+        let seed = position_random(loc);
+        self.get(key).iter().filter_map(|weighted| weighted.pick(seed).cloned()).collect()
+    }
+}
 
 // pub struct MultiPartModelIter<'a, M> {
 
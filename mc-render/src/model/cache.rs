@@ -0,0 +1,680 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::rc::Rc;
+use std::path::Path;
+use std::collections::hash_map::HashMap;
+
+use cgmath::Matrix2;
+use cgmath::Matrix3;
+use cgmath::Matrix4;
+use cgmath::Vector2;
+use cgmath::Vector3;
+use cgmath::Vector4;
+
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use zip::read::ZipArchive;
+
+use crate::assets::data_type::Face;
+use crate::assets::data_type::Rotate90;
+use super::ModelProvider;
+use super::model::Model;
+use super::model::Element;
+use super::model::Cubic;
+use super::model::Rotation;
+use super::model::FaceTexture;
+use super::model::Display;
+use super::model::DisplayContext;
+use super::model::RenderLayer;
+use super::model::TransformedModel;
+use super::model::RefModel;
+use super::model::WeightedModel;
+use super::blockstate::BlockState;
+use super::blockstate::Condition;
+use super::blockstate::Term;
+use super::blockstate::Weighted;
+use super::fluid::FluidTexture;
+use super::fluid::FluidTextures;
+
+
+/**
+ *  bumped whenever the layout below changes; older caches are then ignored
+ */
+pub const CACHE_VERSION: u32 = 2;
+
+const MAGIC: &[u8; 4] = b"MCRC";
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("model cache: bad {}", what))
+}
+
+
+/**
+ *  little-endian encoding of everything a built `ModelProvider` holds
+ */
+pub trait Binary: Sized {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()>;
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self>;
+
+}
+
+macro_rules! binary_number {
+    ($($t: ty),*) => {
+        $(
+            impl Binary for $t {
+
+                fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+
+                fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    r.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+binary_number!(u8, u32, i32, u64, f32);
+
+impl Binary for bool {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (*self as u8).write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(u8::read_from(r)? != 0)
+    }
+}
+
+impl Binary for usize {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (*self as u64).write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(u64::read_from(r)? as usize)
+    }
+}
+
+impl Binary for String {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (self.len() as u32).write_to(w)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let n = u32::read_from(r)?;
+        let mut bytes = Vec::new();
+        r.take(u64::from(n)).read_to_end(&mut bytes)?;
+        if bytes.len() != n as usize {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid("string"))
+    }
+}
+
+impl<T: Binary> Binary for Vec<T> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (self.len() as u32).write_to(w)?;
+        for item in self.iter() {
+            item.write_to(w)?;
+        }
+        Ok(())
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let n = u32::read_from(r)? as usize;
+        let mut list = Vec::with_capacity(n.min(4096));
+        for _ in 0 .. n {
+            list.push(T::read_from(r)?);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Binary> Binary for Option<T> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Some(v) => {
+                true.write_to(w)?;
+                v.write_to(w)
+            },
+            None => false.write_to(w),
+        }
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        if bool::read_from(r)? {
+            Ok(Some(T::read_from(r)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: Binary, B: Binary> Binary for (A, B) {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.0.write_to(w)?;
+        self.1.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let a = A::read_from(r)?;
+        let b = B::read_from(r)?;
+        Ok((a, b))
+    }
+}
+
+impl Binary for Vector2<f32> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.x.write_to(w)?;
+        self.y.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Vector2::new(f32::read_from(r)?, f32::read_from(r)?))
+    }
+}
+
+impl Binary for Vector3<f32> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.x.write_to(w)?;
+        self.y.write_to(w)?;
+        self.z.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Vector3::new(f32::read_from(r)?, f32::read_from(r)?, f32::read_from(r)?))
+    }
+}
+
+impl Binary for Vector4<f32> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.truncate().write_to(w)?;
+        self.w.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Vector3::read_from(r)?.extend(f32::read_from(r)?))
+    }
+}
+
+impl Binary for Matrix2<f32> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.x.write_to(w)?;
+        self.y.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Matrix2::from_cols(Vector2::read_from(r)?, Vector2::read_from(r)?))
+    }
+}
+
+impl Binary for Matrix3<f32> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.x.write_to(w)?;
+        self.y.write_to(w)?;
+        self.z.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Matrix3::from_cols(Vector3::read_from(r)?, Vector3::read_from(r)?, Vector3::read_from(r)?))
+    }
+}
+
+impl Binary for Matrix4<f32> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.x.write_to(w)?;
+        self.y.write_to(w)?;
+        self.z.write_to(w)?;
+        self.w.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Matrix4::from_cols(Vector4::read_from(r)?, Vector4::read_from(r)?, Vector4::read_from(r)?, Vector4::read_from(r)?))
+    }
+}
+
+impl Binary for Face {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (self.index() as u8).write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Face::ALL.get(u8::read_from(r)? as usize).cloned().ok_or_else(|| invalid("face"))
+    }
+}
+
+impl Binary for Rotate90 {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (self.index() as u8).write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Rotate90::from_index(u8::read_from(r)? as usize))
+    }
+}
+
+impl Binary for RenderLayer {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (self.priority() as u8).write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(RenderLayer::from_priority(i32::from(u8::read_from(r)?)))
+    }
+}
+
+impl Binary for Display {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        for context in DisplayContext::ALL.iter() {
+            self.get(*context).write_to(w)?;
+        }
+        Ok(())
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let mut display = Display::default();
+        for context in DisplayContext::ALL.iter() {
+            display.set(*context, Option::read_from(r)?);
+        }
+        Ok(display)
+    }
+}
+
+impl<Tex: Binary> Binary for FaceTexture<Tex> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.uv.write_to(w)?;
+        self.cullface.write_to(w)?;
+        self.rotation.write_to(w)?;
+        self.texture.write_to(w)?;
+        self.tintindex.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(FaceTexture {
+            uv: Matrix2::read_from(r)?,
+            cullface: Option::read_from(r)?,
+            rotation: Rotate90::read_from(r)?,
+            texture: Tex::read_from(r)?,
+            tintindex: Option::read_from(r)?,
+        })
+    }
+}
+
+impl<Tex: Binary> Binary for Element<Tex> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.cubic.from.write_to(w)?;
+        self.cubic.to.write_to(w)?;
+        self.rotation.origin.write_to(w)?;
+        self.rotation.transf.write_to(w)?;
+        for face in self.faces.iter() {
+            face.write_to(w)?;
+        }
+        self.shade.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let cubic = Cubic {
+            from: Vector3::read_from(r)?,
+            to: Vector3::read_from(r)?,
+        };
+        let rotation = Rotation {
+            origin: Vector3::read_from(r)?,
+            transf: Matrix3::read_from(r)?,
+        };
+        let faces = [
+            Option::read_from(r)?, Option::read_from(r)?, Option::read_from(r)?,
+            Option::read_from(r)?, Option::read_from(r)?, Option::read_from(r)?,
+        ];
+        Ok(Element {
+            cubic,
+            rotation,
+            faces,
+            shade: bool::read_from(r)?,
+        })
+    }
+}
+
+impl<Tex: Binary> Binary for Model<Tex> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.ambientocclusion.write_to(w)?;
+        self.elements.write_to(w)?;
+        for occludes in self.occlusion.iter() {
+            occludes.write_to(w)?;
+        }
+        self.layer.write_to(w)?;
+        self.display.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let ambientocclusion = bool::read_from(r)?;
+        let elements = Vec::read_from(r)?;
+        let mut occlusion = [false; 6];
+        for occludes in occlusion.iter_mut() {
+            *occludes = bool::read_from(r)?;
+        }
+        Ok(Model {
+            ambientocclusion,
+            elements,
+            occlusion,
+            layer: RenderLayer::read_from(r)?,
+            display: Display::read_from(r)?,
+        })
+    }
+}
+
+impl Binary for Term {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.property.write_to(w)?;
+        self.values.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Term {
+            property: String::read_from(r)?,
+            values: Vec::read_from(r)?,
+        })
+    }
+}
+
+impl Binary for Condition {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        (self.alternatives().len() as u32).write_to(w)?;
+        for terms in self.alternatives() {
+            terms.write_to(w)?;
+        }
+        Ok(())
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(Condition::from_alternatives(Vec::read_from(r)?))
+    }
+}
+
+
+/**
+ *  models are shared between blockstates through `Rc`s; the cache keeps each one once and
+ *  refers to it by index
+ */
+struct Shared<'a, T> {
+
+    list: Vec<&'a Rc<T>>,
+
+    index: HashMap<*const T, u32>,
+
+}
+
+impl<'a, T> Shared<'a, T> {
+
+    fn new() -> Self {
+        Shared {
+            list: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, rc: &'a Rc<T>) -> u32 {
+        let list = &mut self.list;
+        *self.index.entry(Rc::as_ptr(rc)).or_insert_with(|| {
+            list.push(rc);
+            list.len() as u32 - 1
+        })
+    }
+
+    fn get(&self, rc: &Rc<T>) -> u32 {
+        self.index[&Rc::as_ptr(rc)]
+    }
+}
+
+fn shared<T>(list: &[Rc<T>], r: &mut dyn Read) -> io::Result<Rc<T>> {
+    list.get(u32::read_from(r)? as usize).cloned().ok_or_else(|| invalid("model index"))
+}
+
+fn write_blockstate<Tex>(blockstate: &BlockState<WeightedModel<Tex>>, tmodels: &Shared<TransformedModel<Tex>>, w: &mut dyn Write) -> io::Result<()> {
+    let write_weighted = |weighted: &WeightedModel<Tex>, w: &mut dyn Write| -> io::Result<()> {
+        (weighted.len() as u32).write_to(w)?;
+        for (tmodel, weight) in weighted.entries() {
+            tmodels.get(tmodel).write_to(w)?;
+            weight.write_to(w)?;
+        }
+        Ok(())
+    };
+    let cases = match blockstate {
+        BlockState::Single(weighted) => {
+            0u8.write_to(w)?;
+            return write_weighted(weighted, w);
+        },
+        BlockState::Variants(cases) => {
+            1u8.write_to(w)?;
+            cases
+        },
+        BlockState::MultiPart(cases) => {
+            2u8.write_to(w)?;
+            cases
+        },
+    };
+    (cases.len() as u32).write_to(w)?;
+    for (condition, weighted) in cases.iter() {
+        condition.write_to(w)?;
+        write_weighted(weighted, w)?;
+    }
+    Ok(())
+}
+
+fn read_blockstate<Tex>(tmodels: &[RefModel<Tex>], r: &mut dyn Read) -> io::Result<BlockState<WeightedModel<Tex>>> {
+    let read_weighted = |r: &mut dyn Read| -> io::Result<WeightedModel<Tex>> {
+        let mut weighted = Weighted::new();
+        for _ in 0 .. u32::read_from(r)? {
+            let tmodel = shared(tmodels, r)?;
+            weighted.push(tmodel, u32::read_from(r)?);
+        }
+        Ok(weighted)
+    };
+    let tag = u8::read_from(r)?;
+    if tag == 0 {
+        return Ok(BlockState::Single(read_weighted(r)?));
+    }
+    let n = u32::read_from(r)? as usize;
+    let mut cases = Vec::with_capacity(n.min(4096));
+    for _ in 0 .. n {
+        let condition = Condition::read_from(r)?;
+        cases.push((condition, read_weighted(r)?));
+    }
+    match tag {
+        1 => Ok(BlockState::Variants(cases)),
+        2 => Ok(BlockState::MultiPart(cases.into_iter().map(|(condition, weighted)| (condition, weighted.into_part())).collect())),
+        _ => Err(invalid("blockstate")),
+    }
+}
+
+impl<Tex: Binary> Binary for FluidTexture<Tex> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.still.write_to(w)?;
+        self.flow.write_to(w)?;
+        self.layer.write_to(w)?;
+        self.tinted.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(FluidTexture {
+            still: Tex::read_from(r)?,
+            flow: Tex::read_from(r)?,
+            layer: RenderLayer::read_from(r)?,
+            tinted: bool::read_from(r)?,
+        })
+    }
+}
+
+impl<Tex: Binary> Binary for FluidTextures<Tex> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.water.write_to(w)?;
+        self.lava.write_to(w)
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        Ok(FluidTextures {
+            water: FluidTexture::read_from(r)?,
+            lava: FluidTexture::read_from(r)?,
+        })
+    }
+}
+
+
+/**
+ *  blockstates, models, the placeholder and the fluid textures; block properties and the match mode are not kept
+ */
+impl<Tex: Binary> Binary for ModelProvider<Tex> {
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        let mut tmodels = Shared::new();
+        for blockstate in self.cache.values() {
+            for weighted in blockstate.all() {
+                for tmodel in weighted.iter() {
+                    tmodels.insert(tmodel);
+                }
+            }
+        }
+        if let Some(placeholder) = &self.placeholder {
+            tmodels.insert(placeholder);
+        }
+        let mut models = Shared::new();
+        for tmodel in tmodels.list.iter() {
+            models.insert(&tmodel.model);
+        }
+
+        (models.list.len() as u32).write_to(w)?;
+        for model in models.list.iter() {
+            model.write_to(w)?;
+        }
+        (tmodels.list.len() as u32).write_to(w)?;
+        for tmodel in tmodels.list.iter() {
+            models.get(&tmodel.model).write_to(w)?;
+            tmodel.x.write_to(w)?;
+            tmodel.y.write_to(w)?;
+            tmodel.uvlock.write_to(w)?;
+        }
+        self.placeholder.as_ref().map(|placeholder| tmodels.get(placeholder)).write_to(w)?;
+        self.fluids.write_to(w)?;
+        (self.cache.len() as u32).write_to(w)?;
+        for (name, blockstate) in self.cache.iter() {
+            name.write_to(w)?;
+            write_blockstate(blockstate, &tmodels, w)?;
+        }
+        Ok(())
+    }
+
+    fn read_from(r: &mut dyn Read) -> io::Result<Self> {
+        let models: Vec<Rc<Model<Tex>>> = Vec::<Model<Tex>>::read_from(r)?.into_iter().map(Rc::new).collect();
+        let n = u32::read_from(r)? as usize;
+        let mut tmodels = Vec::with_capacity(n.min(4096));
+        for _ in 0 .. n {
+            let model = shared(&models, r)?;
+            let x = Rotate90::read_from(r)?;
+            let y = Rotate90::read_from(r)?;
+            let uvlock = bool::read_from(r)?;
+            tmodels.push(Rc::new(TransformedModel::from_mxy(model, x, y, uvlock)));
+        }
+        let mut provider = ModelProvider::new();
+        provider.placeholder = match Option::<u32>::read_from(r)? {
+            Some(i) => Some(tmodels.get(i as usize).cloned().ok_or_else(|| invalid("placeholder"))?),
+            None => None,
+        };
+        provider.fluids = Option::read_from(r)?;
+        for _ in 0 .. u32::read_from(r)? {
+            let name = String::read_from(r)?;
+            let blockstate = read_blockstate(&tmodels, r)?;
+            provider.cache.insert(name, blockstate);
+        }
+        Ok(provider)
+    }
+}
+
+
+/**
+ *  FNV-1a of the name, CRC-32 and size of every `assets/` entry of the archives, in order; changes
+ *  whenever a blockstate, model or texture does, and only then
+ */
+pub fn fingerprint<P: AsRef<Path>>(files: &[P]) -> io::Result<u64> {
+    let mut hash = FNV_OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash = (hash ^ u64::from(*b)).wrapping_mul(FNV_PRIME);
+        }
+    };
+    for file in files {
+        let mut zip = ZipArchive::new(File::open(file)?)?;
+        feed(b"\0");
+        for i in 0 .. zip.len() {
+            let entry = zip.by_index_raw(i)?;
+            if entry.name().starts_with("assets/") {
+                feed(entry.name().as_bytes());
+                feed(&entry.crc32().to_le_bytes());
+                feed(&entry.size().to_le_bytes());
+            }
+        }
+    }
+    Ok(hash)
+}
+
+/**
+ *  `value`, deflated, behind a header of `CACHE_VERSION` and `fingerprint`
+ */
+pub fn save<T: Binary>(w: &mut dyn Write, fingerprint: u64, value: &T) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    CACHE_VERSION.write_to(w)?;
+    fingerprint.write_to(w)?;
+    let mut encoder = DeflateEncoder::new(w, Compression::fast());
+    value.write_to(&mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/**
+ *  what `save` wrote, or `None` if the cache is from another version or other assets
+ */
+pub fn load<T: Binary>(r: &mut dyn Read, fingerprint: u64) -> io::Result<Option<T>> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC || u32::read_from(r)? != CACHE_VERSION || u64::read_from(r)? != fingerprint {
+        return Ok(None);
+    }
+    let mut decoder = DeflateDecoder::new(r);
+    T::read_from(&mut decoder).map(Some)
+}
@@ -0,0 +1,204 @@
+use cgmath::Vector2;
+use cgmath::Vector3;
+
+use crate::assets::data_type::Face;
+use super::BlockRenderer;
+use super::block::World;
+use super::block::RenderableBlock;
+use super::model::RenderLayer;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+
+/**
+ *  `still` on flat tops and bottoms, `flow` on sides and tops that slope
+ */
+#[derive(Clone, Debug)]
+pub struct FluidTexture<Tex> {
+
+    pub still: Tex,
+
+    pub flow: Tex,
+
+    pub layer: RenderLayer,
+
+    pub tinted: bool,   // by `tintindex` 0, the biome water color
+
+}
+
+
+#[derive(Clone, Debug)]
+pub struct FluidTextures<Tex> {
+
+    pub water: FluidTexture<Tex>,
+
+    pub lava: FluidTexture<Tex>,
+
+}
+
+impl<Tex> FluidTextures<Tex> {
+
+    pub fn get(&self, fluid: Fluid) -> &FluidTexture<Tex> {
+        match fluid {
+            Fluid::Water => &self.water,
+            Fluid::Lava => &self.lava,
+        }
+    }
+}
+
+
+/**
+ *  surface height of a fluid with blockstate `level`, in blocks; 0 is a source, 8 and more are falling
+ */
+pub fn level_height(level: u8) -> f32 {
+    let amount = if level == 0 || level >= 8 { 8 } else { 8 - level };
+    f32::from(amount) / 9.0
+}
+
+fn same_fluid<'a, B: RenderableBlock<'a>>(world: &'a dyn World<'a, Block = B>, loc: &Vector3<i32>, fluid: Fluid) -> bool {
+    world.height_range().contains(loc.y) && world.get(loc).get_fluid() == Some(fluid)
+}
+
+/**
+ *  a fluid under the same fluid fills its block
+ */
+fn block_height<'a, B: RenderableBlock<'a>>(world: &'a dyn World<'a, Block = B>, loc: &Vector3<i32>, fluid: Fluid) -> f32 {
+    if same_fluid(world, &Vector3::new(loc.x, loc.y + 1, loc.z), fluid) {
+        1.0
+    } else {
+        level_height(world.get(loc).get_fluid_level().unwrap_or(0))
+    }
+}
+
+/**
+ *  height at the north-west corner of `loc`, averaged over the four blocks sharing it the way vanilla
+ *  `FluidBlockRenderer.getFluidHeight` does: nearly full blocks weigh ten times, solid blocks not at all
+ */
+fn corner_height<'a, B: RenderableBlock<'a>>(world: &'a dyn World<'a, Block = B>, loc: &Vector3<i32>, fluid: Fluid) -> f32 {
+    let mut sum = 0.0;
+    let mut weight = 0;
+    for (dx, dz) in [(0, 0), (-1, 0), (0, -1), (-1, -1)].iter() {
+        let pos = Vector3::new(loc.x + dx, loc.y, loc.z + dz);
+        if same_fluid(world, &Vector3::new(pos.x, pos.y + 1, pos.z), fluid) {
+            return 1.0;
+        }
+        if same_fluid(world, &pos, fluid) {
+            let h = block_height(world, &pos, fluid);
+            if h >= 0.8 {
+                sum += h * 10.0;
+                weight += 10;
+            } else {
+                sum += h;
+                weight += 1;
+            }
+        } else if !world.occludes(&pos, &Face::Up) {
+            weight += 1;
+        }
+    }
+    if weight == 0 {
+        0.0
+    } else {
+        sum / weight as f32
+    }
+}
+
+/**
+ *  draw the fluid of the block at `loc`, if it has one, with its surface sloped towards lower neighbours;
+ *  faces against the same fluid or an occluding block are left out
+ */
+pub fn draw<'a, T, E, B> (
+    faces: &[Face],
+    loc: &Vector3<i32>,
+    renderer: &mut dyn BlockRenderer<Texture = T,E = E>,
+    world: &'a dyn World<'a, Block = B>,
+    textures: &FluidTextures<T>,
+) -> Result<(), E>
+where
+    T: Clone + 'a,
+    B: RenderableBlock<'a>,
+{
+    let block = world.get(loc);
+    let fluid = match block.get_fluid() {
+        Some(fluid) => fluid,
+        None => return Ok(()),
+    };
+    let texture = textures.get(fluid);
+    let color = if texture.tinted { block.get_inline_color(0) } else { [255; 4] };
+    let light = block.get_render_light();
+    let range = world.height_range();
+    let visible = |face: &Face| match face.near(loc, &range) {
+        Some(pos) => world.get(&pos).get_fluid() != Some(fluid) && !world.occludes(&pos, &face.opposite()),
+        None => true,
+    };
+    // corners in vanilla's order: north-west, south-west, south-east, north-east
+    let h = [
+        corner_height(world, loc, fluid),
+        corner_height(world, &Vector3::new(loc.x, loc.y, loc.z + 1), fluid),
+        corner_height(world, &Vector3::new(loc.x + 1, loc.y, loc.z + 1), fluid),
+        corner_height(world, &Vector3::new(loc.x + 1, loc.y, loc.z), fluid),
+    ];
+    // vanilla uv (v down the texture) to the bottom-up rows of the texture array
+    let uv = |u: f32, v: f32| Vector2::new(u, 16.0 - v);
+    let top = |i: usize| h[i] * 16.0;
+    renderer.state(texture.layer.priority());
+    for face in faces {
+        if !visible(face) {
+            continue;
+        }
+        match face {
+            Face::Up => {
+                let flow = Vector2::new(h[0] + h[1] - h[2] - h[3], h[0] + h[3] - h[1] - h[2]);
+                let (tex, nw, sw, se, ne) = if flow.x == 0.0 && flow.y == 0.0 {
+                    (texture.still.clone(), uv(0.0, 0.0), uv(0.0, 16.0), uv(16.0, 16.0), uv(16.0, 0.0))
+                } else {
+                    // the middle of the flow texture, turned along the flow
+                    let angle = flow.y.atan2(flow.x) - std::f32::consts::FRAC_PI_2;
+                    let (s, c) = (angle.sin() * 0.25, angle.cos() * 0.25);
+                    (
+                        texture.flow.clone(),
+                        uv(8.0 + (-c - s) * 16.0, 8.0 + (-c + s) * 16.0),
+                        uv(8.0 + (-c + s) * 16.0, 8.0 + (c + s) * 16.0),
+                        uv(8.0 + (c + s) * 16.0, 8.0 + (c - s) * 16.0),
+                        uv(8.0 + (c - s) * 16.0, 8.0 + (-c - s) * 16.0),
+                    )
+                };
+                renderer.draw(
+                    *loc,
+                    Vector3::new(0.0, top(1), 16.0), Vector3::new(16.0, top(2), 16.0), Vector3::new(0.0, top(0), 0.0), Vector3::new(16.0, top(3), 0.0),
+                    sw, se, nw, ne,
+                    tex, color, light
+                )?;
+            },
+            Face::Down => {
+                renderer.draw(
+                    *loc,
+                    Vector3::new(0.0, 0.0, 0.0), Vector3::new(16.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 16.0), Vector3::new(16.0, 0.0, 16.0),
+                    uv(0.0, 0.0), uv(16.0, 0.0), uv(0.0, 16.0), uv(16.0, 16.0),
+                    texture.still.clone(), color, light
+                )?;
+            },
+            _ => {
+                // left and right corner seen from outside, then their positions
+                let (l, r, pl, pr) = match face {
+                    Face::North => (3, 0, Vector2::new(16.0, 0.0), Vector2::new(0.0, 0.0)),
+                    Face::South => (1, 2, Vector2::new(0.0, 16.0), Vector2::new(16.0, 16.0)),
+                    Face::West => (0, 1, Vector2::new(0.0, 0.0), Vector2::new(0.0, 16.0)),
+                    _ => (2, 3, Vector2::new(16.0, 16.0), Vector2::new(16.0, 0.0)),
+                };
+                // the upper half of the flow texture, cut at the surface
+                renderer.draw(
+                    *loc,
+                    Vector3::new(pl.x, 0.0, pl.y), Vector3::new(pr.x, 0.0, pr.y), Vector3::new(pl.x, top(l), pl.y), Vector3::new(pr.x, top(r), pr.y),
+                    uv(0.0, 8.0), uv(8.0, 8.0), uv(0.0, (1.0 - h[l]) * 8.0), uv(8.0, (1.0 - h[r]) * 8.0),
+                    texture.flow.clone(), color, light
+                )?;
+            },
+        }
+    }
+    Ok(())
+}
@@ -2,23 +2,44 @@ pub mod block;
 pub mod blockstate;
 pub mod model;
 pub mod biome;
+pub mod random;
+pub mod cache;
+pub mod fluid;
 
+use std::cell::RefCell;
 use std::collections::hash_map::HashMap;
+use std::collections::hash_set::HashSet;
+use std::io::Read;
 
 use cgmath::Vector2;
 use cgmath::Vector3;
 use cgmath::Zero;
 
+use log::warn;
+
 use crate::assets::data_type::Face;
 use crate::assets::util::Provider;
 use crate::assets::data_raw::ModelRaw;
 use crate::assets::data_raw::BlockStateRaw;
+use crate::assets::data_raw::BlockReportRaw;
 use model::RefModel;
+use model::WeightedModel;
 use model::BlockModelBuilder;
 use model::TextureGen;
+use model::BuildReport;
 use block::World;
+use block::BlockBounds;
 use block::RenderableBlock;
 use blockstate::BlockState;
+use blockstate::Weighted;
+use blockstate::BlockProperties;
+use blockstate::MatchMode;
+use blockstate::MatchError;
+use random::position_random;
+use fluid::Fluid;
+use fluid::FluidTextures;
+
+pub(crate) const LOG_TARGET: &str = "mc_render::model";
 
 pub trait BlockRenderer {
     type Texture: Clone;
@@ -52,7 +73,7 @@ pub fn draw<'a, T, E, B> (
 ) -> Result<(), E>
 where
     T: Clone + 'a,
-    B: RenderableBlock<'a, Model = RefModel<T>>,
+    B: RenderableBlock<'a, Model = WeightedModel<T>>,
 {
     
     let draw_model = |tmodel: &RefModel<T>, block: &B, renderer: &mut dyn BlockRenderer<Texture=T,E=E>| -> Result<(), E> {
@@ -63,8 +84,8 @@ where
                 if let Some(face_tex) = &element.faces[mface.index()] {
                     if let Some(cullface) = &face_tex.cullface {
                         let cullface = tmodel.inv_mapping(cullface.clone());
-                        if let Some(pos) = cullface.near(loc) {
-                            if !world.is_air(&pos) {
+                        if let Some(pos) = cullface.near(loc, &world.height_range()) {
+                            if world.occludes(&pos, &cullface.opposite()) {
                                 continue;
                             }
                         }
@@ -84,10 +105,11 @@ where
                     let mut vt1 = Vector2::zero();
                     let mut vt2 = Vector2::zero();
                     let mut vt3 = Vector2::zero();
-                    face_tex.get_face_vert(tmodel.rotation(mface.clone()), &mut vt0, &mut vt1, &mut vt2, &mut vt3);
+                    let (uv, rotation) = tmodel.face_uv(mface.clone(), face_tex);
+                    model::get_uv_vert(&uv, rotation, &mut vt0, &mut vt1, &mut vt2, &mut vt3);
                     let texture = face_tex.texture.clone();
                     let color = face_tex.tintindex.map(|tintindex| block.get_inline_color(tintindex)).unwrap_or_else(|| [255; 4]);
-                    let light = block.get_light();
+                    let light = block.get_render_light();
                     renderer.draw(*loc, vp0, vp1, vp2, vp3, vt0, vt1, vt2, vt3, texture, color, light)?;
                 }
             }
@@ -99,15 +121,17 @@ where
     if block.is_air() {
         return Ok(());
     }
-    if block.is_water() || block.is_water_logged() {
-        renderer.state(2);
-        for tmodel in block.get_water_models() {
+    let seed = position_random(loc);
+    let fluid = block.get_fluid();
+    if fluid.is_none() && (block.is_water() || block.is_water_logged()) {
+        for tmodel in block.get_water_models().filter_map(|weighted| weighted.pick(seed)) {
+            renderer.state(tmodel.model.layer.priority());
             draw_model(tmodel, &block, renderer)?;
         }
     }
-    if !block.is_water() {
-        renderer.state(0);
-        for tmodel in block.get_models() {
+    if !block.is_water() && fluid != Some(Fluid::Lava) {
+        for tmodel in block.get_models().filter_map(|weighted| weighted.pick(seed)) {
+            renderer.state(tmodel.model.layer.priority());
             draw_model(tmodel, &block, renderer)?;
         }
     }
@@ -115,12 +139,63 @@ where
 }
 
 
+/**
+ *  draw every block of the 16x16x16 section starting at `origin` that lies in `bounds`, with all six
+ *  faces and its fluid if `fluids` is given; blocks enclosed by occluding neighbours are skipped,
+ *  the rest is culled by `cullface`
+ */
+pub fn draw_section<'a, T, E, B> (
+    origin: &Vector3<i32>,
+    bounds: &BlockBounds,
+    renderer: &mut dyn BlockRenderer<Texture = T,E = E>, 
+    world: &'a dyn World<'a, Block = B>,
+    fluids: Option<&FluidTextures<T>>,
+) -> Result<(), E>
+where
+    T: Clone + 'a,
+    B: RenderableBlock<'a, Model = WeightedModel<T>>,
+{
+    let range = world.height_range();
+    let min = Vector3::new(origin.x.max(bounds.min.x), origin.y.max(bounds.min.y).max(range.min), origin.z.max(bounds.min.z));
+    let max = Vector3::new((origin.x + 16).min(bounds.max.x), (origin.y + 16).min(bounds.max.y).min(range.max), (origin.z + 16).min(bounds.max.z));
+    for y in min.y .. max.y {
+        for z in min.z .. max.z {
+            for x in min.x .. max.x {
+                let loc = Vector3::new(x, y, z);
+                if world.is_air(&loc) {
+                    continue;
+                }
+                let enclosed = Face::ALL.iter().all(|face| match face.near(&loc, &range) {
+                    Some(pos) => world.occludes(&pos, &face.opposite()),
+                    None => false,
+                });
+                if !enclosed {
+                    draw(&Face::ALL, &loc, renderer, world)?;
+                    if let Some(fluids) = fluids {
+                        fluid::draw(&Face::ALL, &loc, renderer, world, fluids)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+
 /**
  * 
  */
 pub struct ModelProvider<Tex> {
 
-    cache: HashMap<String, BlockState<String, RefModel<Tex>>>,
+    cache: HashMap<String, BlockState<WeightedModel<Tex>>>,
+
+    placeholder: Option<RefModel<Tex>>,
+
+    fluids: Option<FluidTextures<Tex>>,
+
+    properties: HashMap<String, BlockProperties>,
+
+    mode: MatchMode,
 
 }
 
@@ -128,7 +203,11 @@ impl<Tex> ModelProvider<Tex> {
 
     pub fn new() -> Self {
         ModelProvider {
-            cache: HashMap::new()
+            cache: HashMap::new(),
+            placeholder: None,
+            fluids: None,
+            properties: HashMap::new(),
+            mode: MatchMode::default(),
         }
     }
 
@@ -139,15 +218,38 @@ impl<Tex> ModelProvider<Tex> {
         bs_pvd: &'a mut dyn Provider<Item = BlockStateRaw>,
         mdl_pvd: &'a mut dyn Provider<Item = ModelRaw>,
         tex_gen: &'a mut dyn TextureGen<Texture = Tex>,
-    ) {
+    ) -> BuildReport {
         let mut builder = BlockModelBuilder::new(bs_pvd, mdl_pvd, tex_gen);
+        self.build_with(namespace, blocks, &mut builder);
+        builder.into_report()
+    }
+
+    /**
+     *  `build` with a builder kept between calls, so the models blockstates share are built once;
+     *  the failures go to the builder's report
+     */
+    pub fn build_with<I, B, M, T>(&mut self, namespace: &str, blocks: I, builder: &mut BlockModelBuilder<Tex, B, M, T>)
+    where
+        I: Iterator<Item = String>,
+        B: Provider<Item = BlockStateRaw>,
+        M: Provider<Item = ModelRaw>,
+        T: TextureGen<Texture = Tex>,
+    {
+        if self.placeholder.is_none() {
+            self.placeholder = Some(builder.build_placeholder_model());
+        }
+        if self.fluids.is_none() {
+            self.fluids = Some(builder.build_fluid_textures());
+        }
         for name in blocks {
             let blockstate = match name.as_str() {
-                "water" => BlockState::Single(builder.build_water_model()),
-                "lava" => BlockState::Single(builder.build_lava_model()),
+                "water" => BlockState::Single(Weighted::from(builder.build_water_model())),
+                "lava" => BlockState::Single(Weighted::from(builder.build_lava_model())),
                 _ => match builder.build(name.as_str()) {
                     Ok(blockstate) => blockstate,
                     Err(e) => {
+                        warn!(target: LOG_TARGET, "blockstate `{}:{}`: {}", namespace, name, e);
+                        builder.report_failure(&format!("{}:{}", namespace, name), e);
                         continue;
                     }
                 },
@@ -156,11 +258,179 @@ impl<Tex> ModelProvider<Tex> {
         }
     }
 
-    pub fn get<'a, I: Iterator<Item = &'a str>>(&'a self, name: &str, key: I) -> Vec<RefModel<Tex>> {
+    /**
+     *  stand-in for unknown blocks; available after `build`
+     */
+    pub fn placeholder(&self) -> Option<&RefModel<Tex>> {
+        self.placeholder.as_ref()
+    }
+
+    /**
+     *  textures of `fluid::draw`; available after `build`
+     */
+    pub fn fluids(&self) -> Option<&FluidTextures<Tex>> {
+        self.fluids.as_ref()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.cache.contains_key(name)
+    }
+
+    /**
+     *  every built blockstate, `namespace:name`, in no particular order
+     */
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.cache.keys().map(String::as_str)
+    }
+
+    /**
+     *  models of the default state of `name`, see `BlockState::any`
+     */
+    pub fn get_any(&self, name: &str) -> Vec<WeightedModel<Tex>> {
+        self.cache.get(name).map(|blockstate| blockstate.any(self.properties.get(name))).unwrap_or_default()
+    }
+
+    /**
+     *  property defaults of every block in a `reports/blocks.json`; returns how many blocks it holds
+     */
+    pub fn load_properties<R: Read>(&mut self, reader: R) -> serde_json::Result<usize> {
+        let report: HashMap<String, BlockReportRaw> = serde_json::from_reader(reader)?;
+        let n = report.len();
+        for (name, raw) in report {
+            self.properties.insert(name, BlockProperties::from_raw(&raw));
+        }
+        Ok(n)
+    }
+
+    pub fn set_mode(&mut self, mode: MatchMode) {
+        self.mode = mode;
+    }
+
+    /**
+     *  every weighted alternative of the models of state `key`; `draw` picks one per position.
+     *  lenient, nothing if no variant matches
+     */
+    pub fn get<'a, I: Iterator<Item = &'a str>>(&'a self, name: &str, key: I) -> Vec<WeightedModel<Tex>> {
         if let Some(blockstate) = self.cache.get(name) {
-            blockstate.get(key)
+            blockstate.resolve(key, self.properties.get(name), MatchMode::Lenient).unwrap_or_default()
         } else {
             Vec::new()
         }
     }
-}
\ No newline at end of file
+
+    /**
+     *  `get` in the mode of `set_mode`, telling why a state has no model; unknown blocks have none
+     */
+    pub fn resolve<'a, I: Iterator<Item = &'a str>>(&'a self, name: &str, key: I) -> Result<Vec<WeightedModel<Tex>>, MatchError> {
+        if let Some(blockstate) = self.cache.get(name) {
+            blockstate.resolve(key, self.properties.get(name), self.mode)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /**
+     *  models of state `key` for the block at `loc`, the same alternatives `draw` picks there
+     */
+    pub fn get_at<'a, I: Iterator<Item = &'a str>>(&'a self, name: &str, key: I, loc: &Vector3<i32>) -> Vec<RefModel<Tex>> {
+        let seed = position_random(loc);
+        self.get(name, key).iter().filter_map(|weighted| weighted.pick(seed).cloned()).collect()
+    }
+}
+
+
+/**
+ *  a `ModelProvider` that builds a blockstate, and asks `tex_gen` for its textures, the first
+ *  time it is looked up; `into_inner` hands out what was built so far
+ */
+pub struct LazyModelProvider<Tex, B, M, T> {
+
+    namespace: String,
+
+    inner: RefCell<LazyInner<Tex, B, M, T>>,
+
+}
+
+struct LazyInner<Tex, B, M, T> {
+    pvd: ModelProvider<Tex>,
+    tried: HashSet<String>,
+    builder: BlockModelBuilder<Tex, B, M, T>,
+}
+
+impl<Tex, B, M, T> LazyModelProvider<Tex, B, M, T> 
+where
+    B: Provider<Item = BlockStateRaw>,
+    M: Provider<Item = ModelRaw>,
+    T: TextureGen<Texture = Tex>,
+{
+
+    pub fn new(namespace: &str, bs_pvd: B, mdl_pvd: M, tex_gen: T) -> Self {
+        let mut inner = LazyInner {
+            pvd: ModelProvider::new(),
+            tried: HashSet::new(),
+            builder: BlockModelBuilder::new(bs_pvd, mdl_pvd, tex_gen),
+        };
+        // nothing but the placeholder
+        let LazyInner { pvd, builder, .. } = &mut inner;
+        pvd.build_with(namespace, std::iter::empty(), builder);
+        LazyModelProvider {
+            namespace: namespace.to_string(),
+            inner: RefCell::new(inner),
+        }
+    }
+
+    /**
+     *  build `name` (`namespace:name`) unless it was tried before; false if it does not build
+     */
+    pub fn load(&self, name: &str) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        if !inner.tried.contains(name) {
+            inner.tried.insert(name.to_string());
+            let prefix = format!("{}:", self.namespace);
+            if name.starts_with(&prefix) {
+                let block = name[prefix.len() ..].to_string();
+                let LazyInner { pvd, builder, .. } = &mut *inner;
+                pvd.build_with(&self.namespace, std::iter::once(block), builder);
+            }
+        }
+        inner.pvd.contains(name)
+    }
+
+    /**
+     *  `ModelProvider::get`, building `name` first
+     */
+    pub fn get<'a, I: Iterator<Item = &'a str>>(&self, name: &str, key: I) -> Vec<WeightedModel<Tex>> {
+        self.load(name);
+        let inner = self.inner.borrow();
+        inner.pvd.get(name, key.map(|s| -> &str { s }))
+    }
+
+    /**
+     *  `ModelProvider::resolve`, building `name` first
+     */
+    pub fn resolve<'a, I: Iterator<Item = &'a str>>(&self, name: &str, key: I) -> Result<Vec<WeightedModel<Tex>>, MatchError> {
+        self.load(name);
+        let inner = self.inner.borrow();
+        inner.pvd.resolve(name, key.map(|s| -> &str { s }))
+    }
+
+    /**
+     *  how many blockstates were built
+     */
+    pub fn len(&self) -> usize {
+        self.inner.borrow().pvd.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     *  the blockstates built so far, the texture generator, and the failures of every build
+     */
+    pub fn into_inner(self) -> (ModelProvider<Tex>, T, BuildReport) {
+        let inner = self.inner.into_inner();
+        let (tex_gen, report) = inner.builder.into_inner();
+        (inner.pvd, tex_gen, report)
+    }
+}
@@ -1,34 +1,113 @@
 use std::rc::Rc;
 use std::collections::btree_map::BTreeMap;
-use std::io;
+use std::fmt;
 use std::fmt::Debug;
 
 use cgmath::Matrix2;
 use cgmath::Matrix3;
+use cgmath::Matrix4;
 use cgmath::Vector2;
 use cgmath::Vector3;
+use cgmath::SquareMatrix;
+use cgmath::Deg;
+use cgmath::Rad;
+
+use log::debug;
+
+use serde::Serialize;
 
 use crate::assets::data_type::Axis;
 use crate::assets::data_type::Face;
 use crate::assets::data_type::Rotate90;
 use crate::assets::util::Provider;
+use crate::assets::util::ProvideError;
 use crate::assets::data_raw::FaceTextureRaw;
 use crate::assets::data_raw::ElementRaw;
 use crate::assets::data_raw::ModelRaw;
 use crate::assets::data_raw::BlockStateRaw;
 use crate::assets::data_raw::ApplyRaw;
 use crate::assets::data_raw::Rotation as RawRotation;
+use crate::assets::data_raw::Transform;
+use crate::assets::data_raw::DisplayRaw;
 use super::blockstate::BlockState;
-use super::blockstate::Expression;
+use super::blockstate::Condition;
+use super::blockstate::Weighted;
+use super::fluid::FluidTexture;
+use super::fluid::FluidTextures;
+use super::LOG_TARGET;
 
 
 pub type RefModel<Tex> = Rc<TransformedModel<Tex>>;
 
+pub type WeightedModel<Tex> = Weighted<RefModel<Tex>>;
+
+pub const WHITE_TEXTURE: &str = "mc-render:builtin/white";
+
+
+/**
+ *  how a block is blended, in drawing order
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    #[default]
+    Solid,
+    CutoutMipped,
+    Cutout,
+    Translucent,
+}
+
+impl RenderLayer {
+
+    /**
+     *  `BlockRenderer::state` priority of the layer
+     */
+    pub fn priority(&self) -> i32 {
+        *self as i32
+    }
+
+    pub fn from_priority(prior: i32) -> Self {
+        match prior {
+            i32::MIN ..= 0 => Self::Solid,
+            1 => Self::CutoutMipped,
+            2 => Self::Cutout,
+            _ => Self::Translucent,
+        }
+    }
+}
 
 pub trait TextureGen {
     type Texture;
 
     fn get(&mut self, name: &str) -> Self::Texture;
+
+    /**
+     *  whether every texel of `tex` inside `uv` (`[u0, v0, u1, v1]`, 0 - 16) is opaque
+     */
+    fn is_opaque(&self, tex: &Self::Texture, uv: [f32; 4]) -> bool {
+        let _ = (tex, uv);
+        true
+    }
+
+    fn render_layer(&self, tex: &Self::Texture) -> RenderLayer {
+        let _ = tex;
+        RenderLayer::Solid
+    }
+}
+
+impl<G: TextureGen + ?Sized> TextureGen for &mut G {
+    type Texture = G::Texture;
+
+    fn get(&mut self, name: &str) -> Self::Texture {
+        (**self).get(name)
+    }
+
+    fn is_opaque(&self, tex: &Self::Texture, uv: [f32; 4]) -> bool {
+        (**self).is_opaque(tex, uv)
+    }
+
+    fn render_layer(&self, tex: &Self::Texture) -> RenderLayer {
+        (**self).render_layer(tex)
+    }
 }
 
 
@@ -154,20 +233,40 @@ impl<Tex> TransformedModel<Tex> {
         FACE_ROTATE_INV[self.x.index()][self.y.index()][face.index()].clone()
     }
 
-    pub fn rotation(&self, original_face: Face) -> Rotate90 {
-        let rotate = if !self.uvlock {
-            Rotate90::R0
-        } else {
-            match original_face {
-                Face::Up => self.y.clone(),
-                Face::Down => -self.y.clone(),
-                Face::East => -self.x.clone(),
-                Face::West => self.x.clone(),
-                Face::South => Rotate90::R0,
-                Face::North => Rotate90::R0,
-            }
+    /**
+     *  whether the rotated model hides whatever touches its `face`
+     */
+    pub fn occludes(&self, face: Face) -> bool {
+        self.model.occlusion[self.mapping(face).index()]
+    }
+
+    /**
+     *  uv rectangle and rotation of `face_tex` on model face `original_face`; with `uvlock` both
+     *  are re-mapped through the model rotation, like vanilla, so the texture keeps its world alignment
+     */
+    pub fn face_uv(&self, original_face: Face, face_tex: &FaceTexture<Tex>) -> (Matrix2<f32>, Rotate90) {
+        if !self.uvlock {
+            return (face_tex.uv, face_tex.rotation.clone());
+        }
+        let world_face = self.inv_mapping(original_face.clone());
+        let rotation = self.mapping_transform(&Matrix3::identity());
+        let transf = side_transform(&original_face).invert().unwrap()
+            * rotation.invert().unwrap()
+            * side_transform(&world_face);
+        let lock = |u: f32, v: f32| -> Vector2<f32> {
+            let p = transf * Vector3::new(u / 16.0 - 0.5, v / 16.0 - 0.5, -0.5);
+            Vector2::new(p.x + 0.5, p.y + 0.5) * 16.0
         };
-        -rotate
+        let uv = face_tex.uv;
+        let p0 = lock(uv.x.x, uv.x.y);
+        let p1 = lock(uv.y.x, uv.y.y);
+        let (u0, u1) = if signum(uv.y.x - uv.x.x) == signum(p1.x - p0.x) { (p0.x, p1.x) } else { (p1.x, p0.x) };
+        let (v0, v1) = if signum(uv.y.y - uv.x.y) == signum(p1.y - p0.y) { (p0.y, p1.y) } else { (p1.y, p0.y) };
+
+        let angle = Rad::from(Deg(face_tex.rotation.index() as f32 * 90.0));
+        let dir = transf * Vector3::new(angle.0.cos(), angle.0.sin(), 0.0);
+        let quarters = (Deg::from(Rad(dir.y.atan2(dir.x))).0 / 90.0).round() as i32;
+        (Matrix2::new(u0, v0, u1, v1), Rotate90::from_index((-quarters).rem_euclid(4) as usize))
     }
 
     pub fn mapping_transform(&self, t: &Matrix3<f32>) -> Matrix3<f32> {
@@ -186,6 +285,12 @@ pub struct Model<Tex> {
     pub ambientocclusion: bool,
 
     pub elements: Vec<Element<Tex>>,
+
+    pub occlusion: [bool; 6],   // faces fully covered by opaque texels, by `Face::index`
+
+    pub layer: RenderLayer,     // the most blended layer of all face textures
+
+    pub display: Display,       // inherited from parents
 }
 
 #[derive(Debug)]
@@ -206,44 +311,71 @@ pub struct FaceTexture<Tex> {
 impl<Tex> FaceTexture<Tex> {
 
     pub fn get_face_vert(&self, rotation: Rotate90, bl: &mut Vector2<f32>, br: &mut Vector2<f32>, tl: &mut Vector2<f32>, tr: &mut Vector2<f32>) {
+        get_uv_vert(&self.uv, self.rotation.clone() + rotation, bl, br, tl, tr)
+    }
+}
+
+/**
+ *  corners of the `uv` rectangle turned by `rotation`
+ */
+pub fn get_uv_vert(uv: &Matrix2<f32>, rotation: Rotate90, bl: &mut Vector2<f32>, br: &mut Vector2<f32>, tl: &mut Vector2<f32>, tr: &mut Vector2<f32>) {
         
-        macro_rules! cp_vec2 {
-            ($e:expr, $n1:expr, $n2:expr) => {
-                $e.x = $n1.x;
-                $e.y = $n2.y;
-            };
-        }
+    macro_rules! cp_vec2 {
+        ($e:expr, $n1:expr, $n2:expr) => {
+            $e.x = $n1.x;
+            $e.y = $n2.y;
+        };
+    }
 
-        let rotation = self.rotation.clone() + rotation;
-        match rotation {
-            Rotate90::R0 => {
-                cp_vec2!(bl, self.uv.x, self.uv.x);
-                cp_vec2!(br, self.uv.y, self.uv.x);
-                cp_vec2!(tr, self.uv.y, self.uv.y);
-                cp_vec2!(tl, self.uv.x, self.uv.y);
-            },
-            Rotate90::R270  => {
-                cp_vec2!(br, self.uv.x, self.uv.x);
-                cp_vec2!(tr, self.uv.y, self.uv.x);
-                cp_vec2!(tl, self.uv.y, self.uv.y);
-                cp_vec2!(bl, self.uv.x, self.uv.y);
-            },
-            Rotate90::R180 => {
-                cp_vec2!(tr, self.uv.x, self.uv.x);
-                cp_vec2!(tl, self.uv.y, self.uv.x);
-                cp_vec2!(bl, self.uv.y, self.uv.y);
-                cp_vec2!(br, self.uv.x, self.uv.y);
-            },
-            Rotate90::R90 => {
-                cp_vec2!(tl, self.uv.x, self.uv.x);
-                cp_vec2!(bl, self.uv.y, self.uv.x);
-                cp_vec2!(br, self.uv.y, self.uv.y);
-                cp_vec2!(tr, self.uv.x, self.uv.y);
-            },
-        }
+    match rotation {
+        Rotate90::R0 => {
+            cp_vec2!(bl, uv.x, uv.x);
+            cp_vec2!(br, uv.y, uv.x);
+            cp_vec2!(tr, uv.y, uv.y);
+            cp_vec2!(tl, uv.x, uv.y);
+        },
+        Rotate90::R270  => {
+            cp_vec2!(br, uv.x, uv.x);
+            cp_vec2!(tr, uv.y, uv.x);
+            cp_vec2!(tl, uv.y, uv.y);
+            cp_vec2!(bl, uv.x, uv.y);
+        },
+        Rotate90::R180 => {
+            cp_vec2!(tr, uv.x, uv.x);
+            cp_vec2!(tl, uv.y, uv.x);
+            cp_vec2!(bl, uv.y, uv.y);
+            cp_vec2!(br, uv.x, uv.y);
+        },
+        Rotate90::R90 => {
+            cp_vec2!(tl, uv.x, uv.x);
+            cp_vec2!(bl, uv.y, uv.x);
+            cp_vec2!(br, uv.y, uv.y);
+            cp_vec2!(tr, uv.x, uv.y);
+        },
     }
 }
 
+/**
+ *  vanilla's frame of each face: the south face turned onto it
+ */
+fn side_transform(face: &Face) -> Matrix3<f32> {
+    match face {
+        Face::South => Matrix3::identity(),
+        Face::East => Matrix3::from_angle_y(Deg(90.0)),
+        Face::West => Matrix3::from_angle_y(Deg(-90.0)),
+        Face::North => Matrix3::from_angle_y(Deg(180.0)),
+        Face::Up => Matrix3::from_angle_x(Deg(-90.0)),
+        Face::Down => Matrix3::from_angle_x(Deg(90.0)),
+    }
+}
+
+/**
+ *  like java's `Math.signum`, zero stays zero
+ */
+fn signum(v: f32) -> i32 {
+    if v > 0.0 { 1 } else if v < 0.0 { -1 } else { 0 }
+}
+
 #[derive(Debug)]
 pub struct Cubic<S> {
     
@@ -272,6 +404,25 @@ impl<S: std::cmp::PartialOrd> Cubic<S> {
 
 
 
+impl Cubic<f32> {
+
+    /**
+     *  whether the cubic touches the block boundary at `face` and spans all of it
+     */
+    pub fn covers(&self, face: &Face) -> bool {
+        let (from, to) = (self.from, self.to);
+        let full = |a: f32, b: f32| a.min(b) <= 0.0 && a.max(b) >= 16.0;
+        match face {
+            Face::West => from.x.min(to.x) <= 0.0 && full(from.y, to.y) && full(from.z, to.z),
+            Face::East => from.x.max(to.x) >= 16.0 && full(from.y, to.y) && full(from.z, to.z),
+            Face::Down => from.y.min(to.y) <= 0.0 && full(from.x, to.x) && full(from.z, to.z),
+            Face::Up => from.y.max(to.y) >= 16.0 && full(from.x, to.x) && full(from.z, to.z),
+            Face::North => from.z.min(to.z) <= 0.0 && full(from.x, to.x) && full(from.y, to.y),
+            Face::South => from.z.max(to.z) >= 16.0 && full(from.x, to.x) && full(from.y, to.y),
+        }
+    }
+}
+
 impl<S: Copy> Cubic<S> {
 
     pub fn get_face_vert(&self, face: Face, bl: &mut Vector3<S>, br: &mut Vector3<S>, tl: &mut Vector3<S>, tr: &mut Vector3<S>) {
@@ -338,16 +489,120 @@ pub struct Rotation<S> {
 
 impl Rotation<f32> {
 
+    pub const ANGLES: [f32; 5] = [-45.0, -22.5, 0.0, 22.5, 45.0];
+
+    /**
+     *  with `rescale` the two axes across the rotation axis are stretched by `1 / cos(angle)`,
+     *  so the rotated element spans the block again, as for crossed plants
+     */
     pub fn from_raw<'a>(raw: &'a RawRotation) -> Self {
-        use cgmath::Deg;
+        let (rotation, across) = match raw.axis {
+            Axis::X => (Matrix3::from_angle_x(Deg(raw.angle)), Vector3::new(0.0, 1.0, 1.0)),
+            Axis::Y => (Matrix3::from_angle_y(Deg(raw.angle)), Vector3::new(1.0, 0.0, 1.0)),
+            Axis::Z => (Matrix3::from_angle_z(Deg(raw.angle)), Vector3::new(1.0, 1.0, 0.0)),
+        };
+        let scale = if raw.rescale {
+            across * (1.0 / Rad::from(Deg(raw.angle)).0.cos() - 1.0) + Vector3::new(1.0, 1.0, 1.0)
+        } else {
+            Vector3::new(1.0, 1.0, 1.0)
+        };
         Rotation {
             origin: Vector3::from(raw.origin),
-            transf: match raw.axis {
-                Axis::X => Matrix3::from_angle_x(Deg(raw.angle)),
-                Axis::Y => Matrix3::from_angle_y(Deg(raw.angle)),
-                Axis::Z => Matrix3::from_angle_z(Deg(raw.angle)),
-            }
+            transf: Matrix3::from_diagonal(scale) * rotation,
+        }
+    }
+
+    /**
+     *  vanilla only accepts `ANGLES`
+     */
+    pub fn is_valid(raw: &RawRotation) -> bool {
+        Self::ANGLES.contains(&raw.angle)
+    }
+}
+
+
+/**
+ *  where a model is shown outside the world, the keys of `display` in a model file
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisplayContext {
+    ThirdPersonRightHand,
+    ThirdPersonLeftHand,
+    FirstPersonRightHand,
+    FirstPersonLeftHand,
+    Gui,
+    Head,
+    Ground,
+    Fixed,
+}
+
+impl DisplayContext {
+
+    pub const ALL: [DisplayContext; 8] = [
+        DisplayContext::ThirdPersonRightHand, DisplayContext::ThirdPersonLeftHand,
+        DisplayContext::FirstPersonRightHand, DisplayContext::FirstPersonLeftHand,
+        DisplayContext::Gui, DisplayContext::Head, DisplayContext::Ground, DisplayContext::Fixed,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    fn raw<'a>(&self, raw: &'a DisplayRaw) -> Option<&'a Transform> {
+        match self {
+            DisplayContext::ThirdPersonRightHand => raw.thirdperson_righthand.as_ref(),
+            DisplayContext::ThirdPersonLeftHand => raw.thirdperson_lefthand.as_ref(),
+            DisplayContext::FirstPersonRightHand => raw.firstperson_righthand.as_ref(),
+            DisplayContext::FirstPersonLeftHand => raw.firstperson_lefthand.as_ref(),
+            DisplayContext::Gui => raw.gui.as_ref(),
+            DisplayContext::Head => raw.head.as_ref(),
+            DisplayContext::Ground => raw.ground.as_ref(),
+            DisplayContext::Fixed => raw.fixed.as_ref(),
+        }
+    }
+}
+
+
+/**
+ *  display transforms by `DisplayContext::index`; each one takes the block `[0, 1]^3` to
+ *  blocks around the origin: centered, scaled, rotated x-y-z and then translated
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Display([Option<Matrix4<f32>>; 8]);
+
+impl Display {
+
+    pub fn from_raw(raw: &DisplayRaw) -> Self {
+        let mut display = Display::default();
+        for context in DisplayContext::ALL.iter() {
+            display.0[context.index()] = context.raw(raw).map(Display::matrix);
         }
+        display
+    }
+
+    pub fn get(&self, context: DisplayContext) -> Option<Matrix4<f32>> {
+        self.0[context.index()]
+    }
+
+    pub fn set(&mut self, context: DisplayContext, transform: Option<Matrix4<f32>>) {
+        self.0[context.index()] = transform;
+    }
+
+    /**
+     *  like `get`, but a missing context only centers the block, as vanilla does
+     */
+    pub fn transform(&self, context: DisplayContext) -> Matrix4<f32> {
+        self.get(context).unwrap_or_else(|| Display::matrix(&Transform::default()))
+    }
+
+    pub fn matrix(raw: &Transform) -> Matrix4<f32> {
+        let [rx, ry, rz] = raw.rotation;
+        let [tx, ty, tz] = raw.translation;
+        let [sx, sy, sz] = raw.scale;
+        Matrix4::from_translation(Vector3::new(tx, ty, tz) / 16.0)
+            * Matrix4::from_angle_x(Deg(rx)) * Matrix4::from_angle_y(Deg(ry)) * Matrix4::from_angle_z(Deg(rz))
+            * Matrix4::from_nonuniform_scale(sx, sy, sz)
+            * Matrix4::from_translation(Vector3::new(-0.5, -0.5, -0.5))
     }
 }
 
@@ -365,7 +620,7 @@ pub struct Element<Tex> {
     
 }
 
-// notice that x-rotate is inv-clockwise(right hand) and y-rotate is clockwise
+// like vanilla `ModelRotation`, x and then y turn clockwise looking from the positive axis: Ry(-y) * Rx(-x)
 pub const FACE_ROTATE: [[[Face;6];4];4] = [
     [
         [Face::West,Face::Down,Face::North,Face::South,Face::Up,Face::East],
@@ -374,10 +629,10 @@ pub const FACE_ROTATE: [[[Face;6];4];4] = [
         [Face::North,Face::Down,Face::East,Face::West,Face::Up,Face::South]
     ],
     [
-        [Face::West,Face::South,Face::Down,Face::Up,Face::North,Face::East],
-        [Face::Up,Face::South,Face::West,Face::East,Face::North,Face::Down],
-        [Face::East,Face::South,Face::Up,Face::Down,Face::North,Face::West],
-        [Face::Down,Face::South,Face::East,Face::West,Face::North,Face::Up]
+        [Face::West,Face::North,Face::Up,Face::Down,Face::South,Face::East],
+        [Face::Down,Face::North,Face::West,Face::East,Face::South,Face::Up],
+        [Face::East,Face::North,Face::Down,Face::Up,Face::South,Face::West],
+        [Face::Up,Face::North,Face::East,Face::West,Face::South,Face::Down]
     ],
     [
         [Face::West,Face::Up,Face::South,Face::North,Face::Down,Face::East],
@@ -386,10 +641,10 @@ pub const FACE_ROTATE: [[[Face;6];4];4] = [
         [Face::South,Face::Up,Face::East,Face::West,Face::Down,Face::North]
     ],
     [
-        [Face::West,Face::North,Face::Up,Face::Down,Face::South,Face::East],
-        [Face::Down,Face::North,Face::West,Face::East,Face::South,Face::Up],
-        [Face::East,Face::North,Face::Down,Face::Up,Face::South,Face::West],
-        [Face::Up,Face::North,Face::East,Face::West,Face::South,Face::Down]
+        [Face::West,Face::South,Face::Down,Face::Up,Face::North,Face::East],
+        [Face::Up,Face::South,Face::West,Face::East,Face::North,Face::Down],
+        [Face::East,Face::South,Face::Up,Face::Down,Face::North,Face::West],
+        [Face::Down,Face::South,Face::East,Face::West,Face::North,Face::Up]
     ]
 ];
 
@@ -401,10 +656,10 @@ pub const FACE_ROTATE_INV: [[[Face;6];4];4] = [
         [Face::South,Face::Down,Face::West,Face::East,Face::Up,Face::North]
     ],
     [
-        [Face::West,Face::North,Face::Up,Face::Down,Face::South,Face::East],
-        [Face::North,Face::East,Face::Up,Face::Down,Face::West,Face::South],
-        [Face::East,Face::South,Face::Up,Face::Down,Face::North,Face::West],
-        [Face::South,Face::West,Face::Up,Face::Down,Face::East,Face::North]
+        [Face::West,Face::South,Face::Down,Face::Up,Face::North,Face::East],
+        [Face::North,Face::West,Face::Down,Face::Up,Face::East,Face::South],
+        [Face::East,Face::North,Face::Down,Face::Up,Face::South,Face::West],
+        [Face::South,Face::East,Face::Down,Face::Up,Face::West,Face::North]
     ],
     [
         [Face::West,Face::Up,Face::South,Face::North,Face::Down,Face::East],
@@ -413,10 +668,10 @@ pub const FACE_ROTATE_INV: [[[Face;6];4];4] = [
         [Face::South,Face::Up,Face::East,Face::West,Face::Down,Face::North]
     ],
     [
-        [Face::West,Face::South,Face::Down,Face::Up,Face::North,Face::East],
-        [Face::North,Face::West,Face::Down,Face::Up,Face::East,Face::South],
-        [Face::East,Face::North,Face::Down,Face::Up,Face::South,Face::West],
-        [Face::South,Face::East,Face::Down,Face::Up,Face::West,Face::North]
+        [Face::West,Face::North,Face::Up,Face::Down,Face::South,Face::East],
+        [Face::North,Face::East,Face::Up,Face::Down,Face::West,Face::South],
+        [Face::East,Face::South,Face::Up,Face::Down,Face::North,Face::West],
+        [Face::South,Face::West,Face::Up,Face::Down,Face::East,Face::North]
     ]
 ];
 
@@ -470,16 +725,47 @@ impl<Tex> Element<Tex> {
 impl<Tex> Model<Tex> {
 
     pub fn from_raw<'a>(raw: &ModelRaw, tex_gen: &'a mut dyn TextureGen<Texture = Tex>) -> Self {
+        let mut elements = Vec::with_capacity(raw.elements.len());
+        for element in &raw.elements {
+            elements.push(Element::from_raw(element, tex_gen))
+        }
         Model {
             ambientocclusion: raw.ambientocclusion,
-            elements: {
-                let mut elements = Vec::with_capacity(raw.elements.len());
-                for element in &raw.elements {
-                    elements.push(Element::from_raw(element, tex_gen))
+            occlusion: Model::occlusion(&elements, tex_gen),
+            layer: Model::layer(&elements, tex_gen),
+            display: raw.display.as_ref().map(|display| Display::from_raw(display)).unwrap_or_default(),
+            elements,
+        }
+    }
+
+    pub fn layer(elements: &[Element<Tex>], tex_gen: &dyn TextureGen<Texture = Tex>) -> RenderLayer {
+        elements.iter()
+            .flat_map(|element| element.faces.iter().flatten())
+            .map(|face_tex| tex_gen.render_layer(&face_tex.texture))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /**
+     *  a face occludes if one unrotated element covers it with an opaque texture
+     */
+    pub fn occlusion(elements: &[Element<Tex>], tex_gen: &dyn TextureGen<Texture = Tex>) -> [bool; 6] {
+        let mut occlusion = [false; 6];
+        for face in Face::ALL.iter() {
+            occlusion[face.index()] = elements.iter().any(|element| {
+                if element.rotation.transf != Matrix3::identity() || !element.cubic.covers(face) {
+                    return false;
                 }
-                elements
-            }
+                match &element.faces[face.index()] {
+                    Some(face_tex) => {
+                        let uv = face_tex.uv;
+                        tex_gen.is_opaque(&face_tex.texture, [uv.x.x, uv.x.y, uv.y.x, uv.y.y])
+                    },
+                    None => false,
+                }
+            });
         }
+        occlusion
     }
 }
 
@@ -507,21 +793,127 @@ impl std::fmt::Debug for KVPair {
  */
 
 
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum BuildError {
+    MissingBlockState,
+    MissingModel { model: String },
+    MissingParent { model: String, parent: String },
+    MissingTextureVariable { model: String },
+    InvalidRotation { model: String, angle: f32 },
+    ParseError { file: String, message: String },
+}
+
+impl BuildError {
+
+    fn provide(e: ProvideError, file: &str, missing: impl FnOnce() -> BuildError) -> Self {
+        match e {
+            ProvideError::NotFound => missing(),
+            ProvideError::Invalid(message) => BuildError::ParseError { file: file.to_string(), message },
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBlockState => write!(f, "missing blockstate"),
+            Self::MissingModel { model } => write!(f, "missing model `{}`", model),
+            Self::MissingParent { model, parent } => write!(f, "missing parent `{}` of model `{}`", parent, model),
+            Self::MissingTextureVariable { model } => write!(f, "model `{}` has no texture variables", model),
+            Self::InvalidRotation { model, angle } => write!(f, "model `{}` rotates an element by {}, only -45 to 45 in steps of 22.5 are allowed", model, angle),
+            Self::ParseError { file, message } => write!(f, "`{}`: {}", file, message),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+
+}
+
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FailedBlockState {
+
+    pub name: String,
+
+    pub error: BuildError,
+
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct UnresolvedTexture {
+
+    pub model: String,
+
+    pub reference: String,
+
+}
+
+/**
+ *  what went wrong while building models, for resource pack QA
+ */
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BuildReport {
+
+    pub failed: Vec<FailedBlockState>,
+
+    pub unresolved_textures: Vec<UnresolvedTexture>,
+
+    pub empty_models: Vec<String>,
+
+}
+
+impl BuildReport {
+
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty() && self.unresolved_textures.is_empty() && self.empty_models.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /**
+     *  append the failures of another build
+     */
+    pub fn extend(&mut self, other: BuildReport) {
+        self.failed.extend(other.failed);
+        self.unresolved_textures.extend(other.unresolved_textures);
+        self.empty_models.extend(other.empty_models);
+    }
+}
+
+
 struct IndexTexGen<'a, Tex> {
+    model: &'a str,
     index: &'a BTreeMap<String, String>,
     tex_gen: &'a mut dyn TextureGen<Texture = Tex>,
+    unresolved: &'a mut Vec<UnresolvedTexture>,
 }
 
 impl<'a, Tex> TextureGen for IndexTexGen<'a, Tex> {
     type Texture = Tex;
 
+    fn is_opaque(&self, tex: &Self::Texture, uv: [f32; 4]) -> bool {
+        self.tex_gen.is_opaque(tex, uv)
+    }
+
+    fn render_layer(&self, tex: &Self::Texture) -> RenderLayer {
+        self.tex_gen.render_layer(tex)
+    }
+
     fn get(&mut self, name: &str) -> Self::Texture {
         let mut u = name;
         while u.starts_with('#') {
             u = &u[1..];
             u = match self.index.get(u) {
                 Some(v) => v,
-                None => return self.tex_gen.get(name),
+                None => {
+                    self.unresolved.push(UnresolvedTexture { model: self.model.to_string(), reference: name.to_string() });
+                    return self.tex_gen.get(name)
+                },
             };
         }
         self.tex_gen.get(u)
@@ -529,33 +921,56 @@ impl<'a, Tex> TextureGen for IndexTexGen<'a, Tex> {
 }
 
 
-pub struct BlockModelBuilder<'a, Tex> {
+pub struct BlockModelBuilder<Tex, B, M, T> {
 
-    bs_pvd: &'a mut dyn Provider<Item = BlockStateRaw>,
+    bs_pvd: B,
 
-    mdl_pvd: &'a mut dyn Provider<Item = ModelRaw>,
+    mdl_pvd: M,
 
-    tex_gen: &'a mut dyn TextureGen<Texture = Tex>,
+    tex_gen: T,
 
     mdl_cache: BTreeMap<String, Rc<Model<Tex>>>,
-}
 
-impl<'a, Tex> BlockModelBuilder<'a, Tex> {
+    report: BuildReport,
+}
 
-    pub fn new(
-        bs_pvd: &'a mut dyn Provider<Item = BlockStateRaw>,
-        mdl_pvd: &'a mut dyn Provider<Item = ModelRaw>,
-        tex_gen: &'a mut dyn TextureGen<Texture = Tex>,
-    ) -> Self {
+impl<Tex, B, M, T> BlockModelBuilder<Tex, B, M, T>
+where
+    B: Provider<Item = BlockStateRaw>,
+    M: Provider<Item = ModelRaw>,
+    T: TextureGen<Texture = Tex>,
+{
+
+    /**
+     *  the providers may be owned or `&mut`; models are built once and shared by every blockstate
+     *  the builder builds
+     */
+    pub fn new(bs_pvd: B, mdl_pvd: M, tex_gen: T) -> Self {
         BlockModelBuilder {
             bs_pvd,
             mdl_pvd,
             tex_gen,
-            mdl_cache: BTreeMap::new()
+            mdl_cache: BTreeMap::new(),
+            report: BuildReport::default(),
         }
     }
 
-    pub fn build(&mut self, name: &str) -> io::Result<BlockState<String, Rc<TransformedModel<Tex>>>> {
+    pub fn report_failure(&mut self, name: &str, error: BuildError) {
+        self.report.failed.push(FailedBlockState { name: name.to_string(), error });
+    }
+
+    pub fn into_report(self) -> BuildReport {
+        self.report
+    }
+
+    /**
+     *  the texture generator back, with the failures of every build
+     */
+    pub fn into_inner(self) -> (T, BuildReport) {
+        (self.tex_gen, self.report)
+    }
+
+    pub fn build(&mut self, name: &str) -> Result<BlockState<WeightedModel<Tex>>, BuildError> {
         use std::collections::btree_map::Entry;
         use crate::assets::data_raw::Merge;
 
@@ -622,195 +1037,149 @@ impl<'a, Tex> BlockModelBuilder<'a, Tex> {
         let mdl_pvd = &mut self.mdl_pvd;
         let tex_gen = &mut self.tex_gen;
         let mdl_cache = &mut self.mdl_cache;
-        let mut transf_apply = |v: ApplyRaw| -> io::Result<Rc<TransformedModel<Tex>>> {
-            let v = v.get_fast();
-            
-            let model = match mdl_cache.entry(v.model.clone()) {
-                Entry::Occupied(oc) => oc.get().clone(),
-                Entry::Vacant(vc) => {
-                    let mut mdl_raw = mdl_pvd.provide(vc.key()).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, vc.key().to_string()))?;
-                    while let Some(s) = &mdl_raw.parent {
-                        let parent = mdl_pvd.provide(s).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, s.to_string()))?;
-                        mdl_raw.merge(&parent);
-                    }
-                    let mut itex_gen = IndexTexGen { 
-                        index: mdl_raw.textures.as_ref().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "texture"))?, 
-                        tex_gen: *tex_gen
-                    };
-                    let rcmodel = Rc::new(Model::from_raw(&mdl_raw, &mut itex_gen));
-                    if rcmodel.elements.len() == 0 {
-                        println!("empty model: {}", v.model);
-                    }
-                    vc.insert(rcmodel).clone()
-                }
+        let report = &mut self.report;
+        let mut transf_apply = |v: ApplyRaw| -> Result<WeightedModel<Tex>, BuildError> {
+            let list = match v {
+                ApplyRaw::Item(v) => vec![v],
+                ApplyRaw::Array(list) => list,
             };
-            Ok(Rc::new(TransformedModel::from_mxy(model, v.x.clone(), v.y.clone(), v.uvlock)))
-        };
-
-        if let Some(bs_raw) = self.bs_pvd.provide(name) {
-            match bs_raw {
-                BlockStateRaw::Variants(v_raw) => {
-                    let mut blockstate = BlockState::Variants(Expression::default());
-                    for(keys, apply_raw) in v_raw.into_iter() {
-                        blockstate.insert_group(keys.iter(), transf_apply(apply_raw)?);
-                    }
-                    Ok(blockstate.try_simplify_variant())
-                },
-                BlockStateRaw::MultiPart(m_raw) => {
-                    let mut blockstate = BlockState::MultiPart(Expression::default(), Vec::default());
-                    for(mut group, apply_raw) in m_raw.into_iter() {
-                        let model = transf_apply(apply_raw)?;
-                        blockstate.start_group();
-                        loop {
-                            let it = group.line_iter();
-                            blockstate.insert_group(it, model.clone());
-                            if !group.update_iter() {
-                                break;
-                            }
+            let mut weighted = Weighted::new();
+            for v in list.iter() {
+                let model = match mdl_cache.entry(v.model.clone()) {
+                    Entry::Occupied(oc) => oc.get().clone(),
+                    Entry::Vacant(vc) => {
+                        let name = vc.key().as_str();
+                        let mut mdl_raw = mdl_pvd.try_provide(name).map_err(|e| BuildError::provide(e, name, || BuildError::MissingModel { model: name.to_string() }))?;
+                        while let Some(s) = &mdl_raw.parent {
+                            let parent = mdl_pvd.try_provide(s).map_err(|e| BuildError::provide(e, s, || BuildError::MissingParent { model: name.to_string(), parent: s.to_string() }))?;
+                            mdl_raw.merge(&parent);
+                        }
+                        let invalid = mdl_raw.elements.iter()
+                            .filter_map(|element| element.rotation.as_ref())
+                            .find(|rotation| !Rotation::is_valid(rotation));
+                        if let Some(rotation) = invalid {
+                            return Err(BuildError::InvalidRotation { model: name.to_string(), angle: rotation.angle });
                         }
+                        let mut itex_gen = IndexTexGen { 
+                            model: name,
+                            index: mdl_raw.textures.as_ref().ok_or_else(|| BuildError::MissingTextureVariable { model: name.to_string() })?, 
+                            tex_gen: &mut *tex_gen,
+                            unresolved: &mut report.unresolved_textures,
+                        };
+                        let rcmodel = Rc::new(Model::from_raw(&mdl_raw, &mut itex_gen));
+                        if rcmodel.elements.is_empty() {
+                            debug!(target: LOG_TARGET, "empty model: {}", v.model);
+                            report.empty_models.push(v.model.clone());
+                        }
+                        vc.insert(rcmodel).clone()
                     }
-                    Ok(blockstate)
+                };
+                let tmodel = Rc::new(TransformedModel::from_mxy(model, v.x.clone(), v.y.clone(), v.uvlock));
+                weighted.push(tmodel, v.weight.round() as u32);
+            }
+            Ok(weighted)
+        };
+
+        let bs_raw = self.bs_pvd.try_provide(name).map_err(|e| BuildError::provide(e, name, || BuildError::MissingBlockState))?;
+        match bs_raw {
+            BlockStateRaw::Variants(v_raw) => {
+                let mut variants = Vec::new();
+                for (keys, apply_raw) in v_raw.into_iter() {
+                    variants.push((Condition::from_keys(&keys), transf_apply(apply_raw)?));
+                }
+                Ok(BlockState::Variants(variants).try_simplify_variant())
+            },
+            BlockStateRaw::MultiPart(m_raw) => {
+                let mut parts = Vec::new();
+                for (case, apply_raw) in m_raw.into_iter() {
+                    parts.push((Condition::from_case(&case), transf_apply(apply_raw)?.into_part()));
                 }
+                Ok(BlockState::MultiPart(parts))
             }
-        } else {
-            Err(io::Error::new(io::ErrorKind::NotFound, name.to_string()))
-        }      
+        }
     }
 
     pub fn build_water_model(&mut self) -> Rc<TransformedModel<Tex>> {
-        let model = Rc::new(Model {
-            ambientocclusion: true,
-            elements: vec![
-                Element {
-                    cubic: Cubic {
-                        from: Vector3::new(0.0, 0.0, 0.0),
-                        to:  Vector3::new(16.0, 16.0, 16.0),
-                    },
-                    shade: false,
-                    rotation: Rotation {
-                        origin: Vector3::new(8.0, 8.0, 8.0),
-                        transf: Matrix3::new(
-                            1.0, 0.0, 0.0,
-                            0.0, 1.0, 0.0,
-                            0.0, 0.0, 1.0
-                        )
-                    },
-                    faces: [
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/water_still"),
-                            tintindex: Some(0)
-                        }),
-                        None,
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/water_still"),
-                            tintindex: Some(0)
-                        }),
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/water_still"),
-                            tintindex: Some(0)
-                        }),
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/water_still"),
-                            tintindex: Some(0)
-                        }),
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/water_still"),
-                            tintindex: Some(0)
-                        }),
-                    ]
-                }
-            ]
-        });
-        let tmodel = Rc::new(TransformedModel {
-            model,
-            uvlock: false,
-            x: Rotate90::R0,
-            y: Rotate90::R0,
-        });
-        tmodel
+        self.build_cube_model("block/water_still", false, Some(RenderLayer::Translucent))
     }
 
     pub fn build_lava_model(&mut self) -> Rc<TransformedModel<Tex>> {
+        self.build_cube_model("block/lava_still", false, None)
+    }
+
+    /**
+     *  still and flowing textures of water and lava for `fluid::draw`
+     */
+    pub fn build_fluid_textures(&mut self) -> FluidTextures<Tex> {
+        FluidTextures {
+            water: FluidTexture {
+                still: self.tex_gen.get("block/water_still"),
+                flow: self.tex_gen.get("block/water_flow"),
+                layer: RenderLayer::Translucent,
+                tinted: true,
+            },
+            lava: FluidTexture {
+                still: self.tex_gen.get("block/lava_still"),
+                flow: self.tex_gen.get("block/lava_flow"),
+                layer: RenderLayer::Solid,
+                tinted: false,
+            },
+        }
+    }
+
+    /**
+     *  full cube with `WHITE_TEXTURE` on every face, tinted by `tintindex` 0
+     */
+    pub fn build_placeholder_model(&mut self) -> Rc<TransformedModel<Tex>> {
+        self.build_cube_model(WHITE_TEXTURE, true, None)
+    }
+
+    /**
+     *  `layer` overrides the one derived from `texture`
+     */
+    fn build_cube_model(&mut self, texture: &str, down: bool, layer: Option<RenderLayer>) -> Rc<TransformedModel<Tex>> {
+        let mut face = |enable: bool| if enable {
+            Some(FaceTexture {
+                uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
+                cullface: None,
+                rotation: Rotate90::R0,
+                texture: self.tex_gen.get(texture),
+                tintindex: Some(0)
+            })
+        } else {
+            None
+        };
+        let elements = vec![
+            Element {
+                cubic: Cubic {
+                    from: Vector3::new(0.0, 0.0, 0.0),
+                    to:  Vector3::new(16.0, 16.0, 16.0),
+                },
+                shade: false,
+                rotation: Rotation {
+                    origin: Vector3::new(8.0, 8.0, 8.0),
+                    transf: Matrix3::new(
+                        1.0, 0.0, 0.0,
+                        0.0, 1.0, 0.0,
+                        0.0, 0.0, 1.0
+                    )
+                },
+                faces: [face(true), face(down), face(true), face(true), face(true), face(true)]
+            }
+        ];
         let model = Rc::new(Model {
             ambientocclusion: true,
-            elements: vec![
-                Element {
-                    cubic: Cubic {
-                        from: Vector3::new(0.0, 0.0, 0.0),
-                        to:  Vector3::new(16.0, 16.0, 16.0),
-                    },
-                    shade: false,
-                    rotation: Rotation {
-                        origin: Vector3::new(8.0, 8.0, 8.0),
-                        transf: Matrix3::new(
-                            1.0, 0.0, 0.0,
-                            0.0, 1.0, 0.0,
-                            0.0, 0.0, 1.0
-                        )
-                    },
-                    faces: [
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/lava_still"),
-                            tintindex: Some(0)
-                        }),
-                        None,
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/lava_still"),
-                            tintindex: Some(0)
-                        }),
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/lava_still"),
-                            tintindex: Some(0)
-                        }),
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/lava_still"),
-                            tintindex: Some(0)
-                        }),
-                        Some(FaceTexture {
-                            uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
-                            cullface: None,
-                            rotation: Rotate90::R0,
-                            texture: self.tex_gen.get("block/lava_still"),
-                            tintindex: Some(0)
-                        }),
-                    ]
-                }
-            ]
+            occlusion: Model::occlusion(&elements, &self.tex_gen),
+            layer: layer.unwrap_or_else(|| Model::layer(&elements, &self.tex_gen)),
+            display: Display::default(),
+            elements,
         });
-        let tmodel = Rc::new(TransformedModel {
+        Rc::new(TransformedModel {
             model,
             uvlock: false,
             x: Rotate90::R0,
             y: Rotate90::R0,
-        });
-        tmodel
+        })
     }
 }
 
@@ -0,0 +1,50 @@
+use cgmath::Vector3;
+
+
+const MULTIPLIER: i64 = 0x5_DEEC_E66D;
+
+const ADDEND: i64 = 0xB;
+
+const MASK: i64 = (1 << 48) - 1;
+
+/**
+ *  seed of the block at `loc`, vanilla `MathHelper.getPositionRandom`
+ */
+pub fn position_random(loc: &Vector3<i32>) -> i64 {
+    let i = i64::from(loc.x.wrapping_mul(3_129_871)) ^ i64::from(loc.z).wrapping_mul(116_129_781) ^ i64::from(loc.y);
+    let i = i.wrapping_mul(i).wrapping_mul(42_317_861).wrapping_add(i.wrapping_mul(11));
+    i >> 16
+}
+
+
+/**
+ *  the linear congruential generator of `java.util.Random`, so picks match the game
+ */
+#[derive(Clone, Debug)]
+pub struct JavaRandom {
+
+    seed: i64,
+
+}
+
+impl JavaRandom {
+
+    pub fn new(seed: i64) -> Self {
+        JavaRandom {
+            seed: (seed ^ MULTIPLIER) & MASK,
+        }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    pub fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    pub fn next_long(&mut self) -> i64 {
+        (i64::from(self.next(32)) << 32).wrapping_add(i64::from(self.next(32)))
+    }
+}
@@ -0,0 +1,220 @@
+// fixtures shared by the tests, not every test uses all of them
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+
+use cgmath::Matrix2;
+use cgmath::Matrix3;
+use cgmath::SquareMatrix;
+use cgmath::Vector2;
+use cgmath::Vector3;
+
+use mc_render::assets::util::Provider;
+use mc_render::assets::data_type::Face;
+use mc_render::assets::data_type::Rotate90;
+use mc_render::model::BlockRenderer;
+use mc_render::model::block::World;
+use mc_render::model::block::RenderableBlock;
+use mc_render::model::fluid::Fluid;
+use mc_render::model::model::Model;
+use mc_render::model::model::Element;
+use mc_render::model::model::Cubic;
+use mc_render::model::model::Rotation;
+use mc_render::model::model::FaceTexture;
+use mc_render::model::model::RefModel;
+use mc_render::model::model::TransformedModel;
+use mc_render::model::model::WeightedModel;
+use mc_render::model::model::TextureGen;
+use mc_render::model::model::RenderLayer;
+
+/**
+ *  blockstates or models from json strings by name
+ */
+pub struct JsonProvider<T>(HashMap<&'static str, &'static str>, PhantomData<T>);
+
+impl<T> JsonProvider<T> {
+
+    pub fn new(list: Vec<(&'static str, &'static str)>) -> Self {
+        JsonProvider(list.into_iter().collect(), PhantomData)
+    }
+}
+
+impl<T: DeserializeOwned> Provider for JsonProvider<T> {
+    type Item = T;
+
+    fn provide(&mut self, name: &str) -> Option<Self::Item> {
+        self.0.get(name).and_then(|s| serde_json::from_str(s).ok())
+    }
+}
+
+/**
+ *  textures are their names
+ */
+pub struct NameTexGen;
+
+impl TextureGen for NameTexGen {
+    type Texture = String;
+
+    fn get(&mut self, name: &str) -> Self::Texture {
+        name.to_string()
+    }
+}
+
+/**
+ *  `glass` is a transparent cutout texture, anything else opaque `stone`
+ */
+pub struct AlphaTexGen;
+
+impl TextureGen for AlphaTexGen {
+    type Texture = &'static str;
+
+    fn get(&mut self, name: &str) -> Self::Texture {
+        if name == "glass" { "glass" } else { "stone" }
+    }
+
+    fn is_opaque(&self, tex: &Self::Texture, _uv: [f32; 4]) -> bool {
+        *tex != "glass"
+    }
+
+    fn render_layer(&self, tex: &Self::Texture) -> RenderLayer {
+        if *tex == "glass" { RenderLayer::Cutout } else { RenderLayer::Solid }
+    }
+}
+
+/**
+ *  a block `height` texels high, all faces culled but a lower top
+ */
+pub fn block_model(texture: &str, height: f32) -> RefModel<&'static str> {
+    let mut tex_gen = AlphaTexGen;
+    let mut face = |face: &Face| Some(FaceTexture {
+        uv: Matrix2::new(0.0, 0.0, 16.0, 16.0),
+        cullface: if *face == Face::Up && height < 16.0 { None } else { Some(face.clone()) },
+        rotation: Rotate90::R0,
+        texture: tex_gen.get(texture),
+        tintindex: None,
+    });
+    let [f0, f1, f2, f3, f4, f5] = Face::ALL;
+    let elements = vec![Element {
+        cubic: Cubic { from: Vector3::new(0.0, 0.0, 0.0), to: Vector3::new(16.0, height, 16.0) },
+        rotation: Rotation { origin: Vector3::new(8.0, 8.0, 8.0), transf: Matrix3::identity() },
+        faces: [face(&f0), face(&f1), face(&f2), face(&f3), face(&f4), face(&f5)],
+        shade: true,
+    }];
+    let model = Model {
+        ambientocclusion: true,
+        occlusion: Model::occlusion(&elements, &tex_gen),
+        layer: Model::layer(&elements, &tex_gen),
+        display: Default::default(),
+        elements,
+    };
+    Rc::new(TransformedModel::from_mxy(Rc::new(model), Rotate90::R0, Rotate90::R0, false))
+}
+
+/**
+ *  blocks by the index of their models, and fluids with their level
+ */
+#[derive(Default)]
+pub struct Voxels {
+    pub blocks: HashMap<(i32, i32, i32), usize>,
+    pub models: Vec<Vec<WeightedModel<&'static str>>>,
+    pub fluids: HashMap<(i32, i32, i32), (Fluid, u8)>,
+}
+
+pub struct Block<'a> {
+    model: Option<&'a [WeightedModel<&'static str>]>,
+    fluid: Option<(Fluid, u8)>,
+}
+
+impl<'a> RenderableBlock<'a> for Block<'a> {
+    type Model = WeightedModel<&'static str>;
+
+    fn is_air(&self) -> bool {
+        self.model.is_none() && self.fluid.is_none()
+    }
+
+    fn is_water(&self) -> bool {
+        self.get_fluid() == Some(Fluid::Water)
+    }
+
+    fn is_water_logged(&self) -> bool {
+        false
+    }
+
+    fn get_models(&self) -> std::slice::Iter<'a, Self::Model> {
+        self.model.unwrap_or(&[]).iter()
+    }
+
+    fn get_water_models(&self) -> std::slice::Iter<'a, Self::Model> {
+        [].iter()
+    }
+
+    fn get_inline_color(&self, _tintindex: usize) -> [u8; 4] {
+        [63, 118, 228, 255]
+    }
+
+    fn get_light(&self) -> u8 {
+        0
+    }
+
+    fn get_fluid(&self) -> Option<Fluid> {
+        self.fluid.map(|(fluid, _)| fluid)
+    }
+
+    fn get_fluid_level(&self) -> Option<u8> {
+        self.fluid.map(|(_, level)| level)
+    }
+
+    fn occludes(&self, face: &Face) -> bool {
+        self.get_models().any(|m| m.iter().all(|t| t.occludes(face.clone())))
+    }
+}
+
+impl<'a> World<'a> for Voxels {
+    type Block = Block<'a>;
+
+    fn get(&'a self, loc: &Vector3<i32>) -> Self::Block {
+        let key = (loc.x, loc.y, loc.z);
+        Block {
+            model: self.blocks.get(&key).map(|i| self.models[*i].as_slice()),
+            fluid: self.fluids.get(&key).cloned(),
+        }
+    }
+}
+
+pub struct Quad {
+    pub pos: [Vector3<f32>; 4],
+    pub tex: &'static str,
+    pub color: [u8; 4],
+}
+
+/**
+ *  every quad drawn, in order
+ */
+#[derive(Default)]
+pub struct Quads(pub Vec<Quad>);
+
+impl BlockRenderer for Quads {
+    type Texture = &'static str;
+    type E = ();
+
+    fn state(&mut self, prior: i32) -> i32 {
+        prior
+    }
+
+    fn draw(
+        &mut self,
+        _loc: Vector3<i32>,
+        vp0: Vector3<f32>, vp1: Vector3<f32>, vp2: Vector3<f32>, vp3: Vector3<f32>,
+        _vt0: Vector2<f32>, _vt1: Vector2<f32>, _vt2: Vector2<f32>, _vt3: Vector2<f32>,
+        tex: Self::Texture,
+        color: [u8; 4],
+        _light: u8
+    ) -> Result<(), Self::E> {
+        self.0.push(Quad { pos: [vp0, vp1, vp2, vp3], tex, color });
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use image::Rgba;
+use image::RgbaImage;
+
+use mc_render::glrender::antialias;
+use mc_render::glrender::antialias::Antialias;
+
+#[test]
+fn test_downsample() {
+    let mut image = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 0]));
+    image.put_pixel(0, 0, Rgba([200, 100, 0, 255]));
+    image.put_pixel(1, 1, Rgba([100, 100, 100, 255]));
+    image.put_pixel(2, 0, Rgba([40, 40, 40, 255]));
+    image.put_pixel(3, 0, Rgba([40, 40, 40, 255]));
+    image.put_pixel(2, 1, Rgba([40, 40, 40, 255]));
+    image.put_pixel(3, 1, Rgba([40, 40, 40, 255]));
+    let small = antialias::downsample(&image, 2);
+    assert_eq!(small.dimensions(), (2, 1));
+    // half covered: the colour of what is there, half as opaque
+    assert_eq!(small.get_pixel(0, 0), &Rgba([150, 100, 50, 128]));
+    assert_eq!(small.get_pixel(1, 0), &Rgba([40, 40, 40, 255]));
+    assert_eq!(antialias::downsample(&image, 1).into_raw(), image.into_raw());
+}
+
+#[test]
+fn test_antialias_default() {
+    assert!(Antialias::default().is_none());
+    assert!(!Antialias { msaa: 4, .. Default::default() }.is_none());
+    assert!(!Antialias { supersample: 2, .. Default::default() }.is_none());
+}
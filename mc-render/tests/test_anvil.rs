@@ -0,0 +1,250 @@
+use std::io::Cursor;
+use std::io::Write;
+
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+
+use mc_render::anvil::nbt;
+use mc_render::anvil::nbt::Tag;
+use mc_render::anvil::region::Region;
+use mc_render::anvil::chunk::Chunk;
+use mc_render::anvil::chunk::BiomeRef;
+use mc_render::model::block::HeightRange;
+
+fn write_payload(out: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::End => {},
+        Tag::Byte(v) => out.push(*v as u8),
+        Tag::Short(v) => out.extend(&v.to_be_bytes()),
+        Tag::Int(v) => out.extend(&v.to_be_bytes()),
+        Tag::Long(v) => out.extend(&v.to_be_bytes()),
+        Tag::Float(v) => out.extend(&v.to_be_bytes()),
+        Tag::Double(v) => out.extend(&v.to_be_bytes()),
+        Tag::ByteArray(list) => {
+            out.extend(&(list.len() as i32).to_be_bytes());
+            out.extend(list.iter().map(|v| *v as u8));
+        },
+        Tag::String(s) => {
+            out.extend(&(s.len() as u16).to_be_bytes());
+            out.extend(s.as_bytes());
+        },
+        Tag::List(list) => {
+            out.push(list.first().map(id).unwrap_or(0));
+            out.extend(&(list.len() as i32).to_be_bytes());
+            for t in list {
+                write_payload(out, t);
+            }
+        },
+        Tag::Compound(map) => {
+            for (k, v) in map {
+                out.push(id(v));
+                write_payload(out, &Tag::String(k.clone()));
+                write_payload(out, v);
+            }
+            out.push(0);
+        },
+        Tag::IntArray(list) => {
+            out.extend(&(list.len() as i32).to_be_bytes());
+            for v in list {
+                out.extend(&v.to_be_bytes());
+            }
+        },
+        Tag::LongArray(list) => {
+            out.extend(&(list.len() as i32).to_be_bytes());
+            for v in list {
+                out.extend(&v.to_be_bytes());
+            }
+        },
+    }
+}
+
+fn id(tag: &Tag) -> u8 {
+    match tag {
+        Tag::End => 0,
+        Tag::Byte(_) => 1,
+        Tag::Short(_) => 2,
+        Tag::Int(_) => 3,
+        Tag::Long(_) => 4,
+        Tag::Float(_) => 5,
+        Tag::Double(_) => 6,
+        Tag::ByteArray(_) => 7,
+        Tag::String(_) => 8,
+        Tag::List(_) => 9,
+        Tag::Compound(_) => 10,
+        Tag::IntArray(_) => 11,
+        Tag::LongArray(_) => 12,
+    }
+}
+
+fn compound(list: Vec<(&str, Tag)>) -> Tag {
+    Tag::Compound(list.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn block(name: &str) -> Tag {
+    compound(vec![("Name", Tag::String(name.to_string()))])
+}
+
+fn pack(values: &[u64], bits: usize, spanning: bool) -> Vec<i64> {
+    let mut res = Vec::new();
+    if spanning {
+        res.resize((values.len() * bits).div_ceil(64), 0u64);
+        for (i, v) in values.iter().enumerate() {
+            let offset = i * bits;
+            res[offset / 64] |= v << (offset % 64);
+            if offset % 64 + bits > 64 {
+                res[offset / 64 + 1] |= v >> (64 - offset % 64);
+            }
+        }
+    } else {
+        let per_long = 64 / bits;
+        res.resize(values.len().div_ceil(per_long), 0u64);
+        for (i, v) in values.iter().enumerate() {
+            res[i / per_long] |= v << ((i % per_long) * bits);
+        }
+    }
+    res.into_iter().map(|v| v as i64).collect()
+}
+
+fn region_of(root: &Tag) -> Vec<u8> {
+    let mut raw = Vec::new();
+    raw.push(10);
+    write_payload(&mut raw, &Tag::String(String::new()));
+    write_payload(&mut raw, root);
+    let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
+    e.write_all(&raw).unwrap();
+    let data = e.finish().unwrap();
+
+    let mut file = vec![0u8; 8192];
+    let sectors = (data.len() + 5).div_ceil(4096);
+    file[0..4].copy_from_slice(&((2u32 << 8) | sectors as u32).to_be_bytes());
+    file.extend(&((data.len() + 1) as u32).to_be_bytes());
+    file.push(2);
+    file.extend(&data);
+    file.resize(8192 + sectors * 4096, 0);
+    file
+}
+
+#[test]
+fn test_nbt_read() {
+    let tag = compound(vec![("a", Tag::Int(7)), ("b", Tag::List(vec![Tag::String("x".to_string())]))]);
+    let mut raw = vec![10];
+    write_payload(&mut raw, &Tag::String("root".to_string()));
+    write_payload(&mut raw, &tag);
+    let (name, read) = nbt::read(&mut Cursor::new(raw)).unwrap();
+    assert_eq!(name, "root");
+    assert_eq!(read, tag);
+    assert_eq!(read.get("a").and_then(Tag::as_i32), Some(7));
+}
+
+#[test]
+fn test_chunk_extended() {
+    // stone below y = 2, one grass block on top at (3, 2, 5)
+    let mut blocks = vec![0u64; 4096];
+    for b in &mut blocks[0 .. 512] {
+        *b = 1;
+    }
+    blocks[(2 * 16 + 5) * 16 + 3] = 2;
+    let mut heights = vec![(2 - HeightRange::EXTENDED.min) as u64; 256];
+    heights[5 * 16 + 3] = (3 - HeightRange::EXTENDED.min) as u64;
+    let section = compound(vec![
+        ("Y", Tag::Int(0)),
+        ("block_states", compound(vec![
+            ("palette", Tag::List(vec![block("minecraft:air"), block("minecraft:stone"), compound(vec![
+                ("Name", Tag::String("minecraft:grass_block".to_string())),
+                ("Properties", compound(vec![("snowy", Tag::String("false".to_string()))])),
+            ])])),
+            ("data", Tag::LongArray(pack(&blocks, 4, false))),
+        ])),
+        ("biomes", compound(vec![
+            ("palette", Tag::List(vec![Tag::String("minecraft:plains".to_string()), Tag::String("minecraft:dark_forest".to_string())])),
+            ("data", Tag::LongArray(pack(&[1u64; 64], 1, false))),
+        ])),
+    ]);
+    let root = compound(vec![
+        ("DataVersion", Tag::Int(2975)),
+        ("xPos", Tag::Int(0)),
+        ("zPos", Tag::Int(0)),
+        ("yPos", Tag::Int(-4)),
+        ("sections", Tag::List(vec![section])),
+        ("Heightmaps", compound(vec![("WORLD_SURFACE", Tag::LongArray(pack(&heights, 9, false)))])),
+    ]);
+
+    let mut region = Region::new(Cursor::new(region_of(&root))).unwrap();
+    assert!(region.has_chunk(0, 0));
+    assert!(!region.has_chunk(1, 0));
+    assert!(region.read_chunk(1, 0).unwrap().is_none());
+    assert!(!region.has_chunk(32, 0));
+    assert!(region.read_chunk(0, 32).unwrap().is_none());
+    let chunk = Chunk::from_nbt(&region.read_chunk(0, 0).unwrap().unwrap()).unwrap();
+
+    assert_eq!(chunk.height_range(), HeightRange::EXTENDED);
+    let name = |x, y, z| chunk.block(x, y, z).map(|i| chunk.palette()[i].name.as_str());
+    assert_eq!(name(0, 0, 0), Some("minecraft:stone"));
+    assert_eq!(name(0, 2, 0), Some("minecraft:air"));
+    assert_eq!(name(3, 2, 5), Some("minecraft:grass_block"));
+    assert_eq!(name(0, -20, 0), None);
+    let grass = &chunk.palette()[chunk.block(3, 2, 5).unwrap()];
+    assert_eq!(grass.property("snowy"), Some("false"));
+    assert_eq!(chunk.height("WORLD_SURFACE", 0, 0), Some(2));
+    assert_eq!(chunk.height("WORLD_SURFACE", 3, 5), Some(3));
+    assert_eq!(chunk.height("OCEAN_FLOOR", 0, 0), None);
+    assert_eq!(chunk.biome(3, 2, 5), Some(BiomeRef::Name("minecraft:dark_forest")));
+}
+
+#[test]
+fn test_region_short() {
+    for len in &[0, 100] {
+        let mut region = Region::new(Cursor::new(vec![1u8; *len])).unwrap();
+        assert!(!region.has_chunk(0, 0));
+        assert!(region.read_chunk(0, 0).unwrap().is_none());
+    }
+}
+
+#[test]
+fn test_chunk_legacy_spanning() {
+    // 17 states need 5 bits, which do not divide 64
+    let palette: Vec<Tag> = (0 .. 17).map(|i| block(&format!("minecraft:b{}", i))).collect();
+    let blocks: Vec<u64> = (0 .. 4096).map(|i| (i % 17) as u64).collect();
+    let section = compound(vec![
+        ("Y", Tag::Int(1)),
+        ("Palette", Tag::List(palette)),
+        ("BlockStates", Tag::LongArray(pack(&blocks, 5, true))),
+    ]);
+    let root = compound(vec![
+        ("DataVersion", Tag::Int(1976)),
+        ("Level", compound(vec![
+            ("xPos", Tag::Int(0)),
+            ("zPos", Tag::Int(0)),
+            ("Sections", Tag::List(vec![section])),
+        ])),
+    ]);
+    let chunk = Chunk::from_nbt(&root).unwrap();
+    assert_eq!(chunk.height_range(), HeightRange::LEGACY);
+    for &(x, y, z) in &[(0, 16, 0), (12, 16, 0), (13, 17, 0), (15, 31, 15), (7, 20, 9)] {
+        let i = ((y as usize - 16) * 16 + z) * 16 + x;
+        let entry = &chunk.palette()[chunk.block(x, y, z).unwrap()];
+        assert_eq!(entry.name, format!("minecraft:b{}", i % 17));
+    }
+}
+
+#[test]
+fn test_chunk_pre_flattening() {
+    let section = compound(vec![
+        ("Y", Tag::Int(0)),
+        ("Blocks", Tag::ByteArray(vec![1; 4096])),
+        ("Data", Tag::ByteArray(vec![0; 2048])),
+    ]);
+    let root = compound(vec![
+        ("DataVersion", Tag::Int(1343)),
+        ("Level", compound(vec![
+            ("xPos", Tag::Int(0)),
+            ("zPos", Tag::Int(0)),
+            ("Sections", Tag::List(vec![section])),
+            ("Biomes", Tag::IntArray(vec![300; 256])),
+        ])),
+    ]);
+    let chunk = Chunk::from_nbt(&root).unwrap();
+    assert!(chunk.is_pre_flattening());
+    assert_eq!(chunk.biome_ids().len(), 256);
+    assert_eq!(chunk.biome(0, 0, 0), Some(BiomeRef::Id(300)));
+}
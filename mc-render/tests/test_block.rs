@@ -0,0 +1,20 @@
+use cgmath::Vector3;
+
+use mc_render::assets::data_type::Face;
+use mc_render::model::block::HeightRange;
+
+#[test]
+fn test_near_height_range() {
+    let legacy = HeightRange::LEGACY;
+    assert_eq!(Face::Down.near(&Vector3::new(0, 0, 0), &legacy), None);
+    assert_eq!(Face::Up.near(&Vector3::new(0, 255, 0), &legacy), None);
+    assert_eq!(Face::Up.near(&Vector3::new(0, 254, 0), &legacy), Some(Vector3::new(0, 255, 0)));
+
+    let extended = HeightRange::EXTENDED;
+    assert_eq!(Face::Down.near(&Vector3::new(0, 0, 0), &extended), Some(Vector3::new(0, -1, 0)));
+    assert_eq!(Face::Down.near(&Vector3::new(0, -64, 0), &extended), None);
+    assert_eq!(Face::Up.near(&Vector3::new(0, 255, 0), &extended), Some(Vector3::new(0, 256, 0)));
+    assert_eq!(Face::Up.near(&Vector3::new(0, 319, 0), &extended), None);
+    assert_eq!(extended.span(), 384);
+    assert_eq!(extended.center(), 128);
+}
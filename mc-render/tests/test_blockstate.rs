@@ -0,0 +1,141 @@
+use mc_render::assets::data_type::Face;
+use mc_render::assets::data_raw::BlockStateRaw;
+use mc_render::assets::data_raw::BlockReportRaw;
+use mc_render::model::blockstate::BlockState;
+use mc_render::model::blockstate::BlockProperties;
+use mc_render::model::blockstate::Condition;
+use mc_render::model::blockstate::MatchMode;
+use mc_render::model::blockstate::MatchError;
+use mc_render::model::blockstate::BlockStateKey;
+use mc_render::model::blockstate::Half;
+
+fn redstone_wire() -> BlockState<usize> {
+    let ifile = std::fs::File::open("tests/redstone_wire.json").unwrap();
+    let data: BlockStateRaw = serde_json::from_reader(ifile).unwrap();
+    let data = if let BlockStateRaw::MultiPart(v) = data { v } else { panic!("multipart expected") };
+    BlockState::MultiPart(data.into_iter().enumerate().map(|(i, (case, _))| (Condition::from_case(&case), i)).collect())
+}
+
+#[test]
+fn test_multipart_or() {
+    let wire = redstone_wire();
+    // dot by the second `OR` alternative, which holds together with other properties
+    assert_eq!(wire.get("east=side,north=side,power=3,south=none,west=none".split(',')), vec![0, 1, 3]);
+    assert_eq!(wire.get("east=none,north=none,power=0,south=none,west=none".split(',')), vec![0]);
+    assert_eq!(wire.get("east=none,north=up,power=15,south=side,west=none".split(',')), vec![1, 2, 5]);
+
+    let mismatches = wire.mismatches("east=none,north=none,power=0,south=none,west=none".split(','));
+    assert_eq!(mismatches.len(), 8);
+    let up = mismatches.iter().find(|m| m.condition.to_string() == "north=up").unwrap();
+    assert_eq!(up.failed.len(), 1);
+    assert_eq!(up.failed[0].1.as_deref(), Some("none"));
+}
+
+#[test]
+fn test_multipart_any() {
+    let wire = redstone_wire();
+    // the state of the first terms, all `none`: just the dot
+    assert_eq!(wire.any(None), vec![0]);
+
+    let raw: BlockReportRaw = serde_json::from_str(r#"{
+        "properties": {"east": ["up", "side", "none"], "north": ["up", "side", "none"], "power": ["0", "15"], "south": ["up", "side", "none"], "west": ["up", "side", "none"]},
+        "states": [
+            {"id": 1, "default": true, "properties": {"east": "side", "north": "side", "power": "0", "south": "side", "west": "side"}}
+        ]
+    }"#).unwrap();
+    let properties = BlockProperties::from_raw(&raw);
+    // every part that holds in the default state
+    assert_eq!(wire.any(Some(&properties)), vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_variants() {
+    let keys = |s: &'static str| -> Vec<&'static str> { if s.is_empty() { Vec::new() } else { s.split(',').collect() } };
+    let grass = BlockState::Variants(vec![
+        (Condition::from_keys(&keys("")), 0),
+        (Condition::from_keys(&keys("snowy=true")), 1),
+    ]);
+    assert_eq!(grass.get("snowy=true".split(',')), vec![1]);
+    assert_eq!(grass.get("snowy=false".split(',')), vec![0]);
+
+    // more properties than bits in a mask
+    let mut variants = Vec::new();
+    for i in 0 .. 100 {
+        let key = format!("a={},b={}", i, i % 7);
+        let key: Vec<&str> = key.split(',').collect();
+        variants.push((Condition::from_keys(&key), i));
+    }
+    let many = BlockState::Variants(variants);
+    assert_eq!(many.get("a=99,b=1,c=x".split(',')), vec![99]);
+    assert!(many.get("a=99,b=2".split(',')).is_empty());
+    let mismatches = many.mismatches("a=99".split(','));
+    assert_eq!(mismatches.len(), 100);
+    let last = mismatches.iter().find(|m| m.condition.to_string() == "a=99,b=1").unwrap();
+    assert_eq!(last.failed[0].0.property, "b");
+    assert_eq!(last.failed[0].1, None);
+    assert_eq!(last.to_string(), "`a=99,b=1`: b=1 is missing");
+}
+
+#[test]
+fn test_default_state() {
+    let raw: BlockReportRaw = serde_json::from_str(r#"{
+        "properties": {"facing": ["north", "south"], "lit": ["true", "false"]},
+        "states": [
+            {"id": 1, "properties": {"facing": "north", "lit": "true"}},
+            {"id": 2, "default": true, "properties": {"facing": "north", "lit": "false"}},
+            {"id": 3, "properties": {"facing": "south", "lit": "true"}},
+            {"id": 4, "properties": {"facing": "south", "lit": "false"}}
+        ]
+    }"#).unwrap();
+    let properties = BlockProperties::from_raw(&raw);
+    assert_eq!(properties.default.get("lit").map(String::as_str), Some("false"));
+
+    let mut variants = Vec::new();
+    for (i, key) in ["facing=north,lit=false", "facing=north,lit=true", "facing=south,lit=false", "facing=south,lit=true"].iter().enumerate() {
+        let key: Vec<&str> = key.split(',').collect();
+        variants.push((Condition::from_keys(&key), i));
+    }
+    let furnace = BlockState::Variants(variants);
+
+    // `lit` is left out: nothing without the defaults, the default value with them
+    assert!(furnace.get("facing=south".split(',')).is_empty());
+    match furnace.resolve("facing=south".split(','), None, MatchMode::Lenient) {
+        Err(MatchError::NoMatch(mismatches)) => assert_eq!(mismatches.len(), 4),
+        r => panic!("{:?}", r),
+    }
+    assert_eq!(furnace.resolve("facing=south".split(','), Some(&properties), MatchMode::Lenient).unwrap(), vec![2]);
+    assert_eq!(furnace.resolve("facing=south,lit=true".split(','), Some(&properties), MatchMode::Strict).unwrap(), vec![3]);
+
+    // extra properties and values only fail the strict mode
+    assert_eq!(furnace.resolve("facing=south,powered=true".split(','), Some(&properties), MatchMode::Lenient).unwrap(), vec![2]);
+    match furnace.resolve("facing=south,powered=true".split(','), Some(&properties), MatchMode::Strict) {
+        Err(MatchError::UnknownProperty(property)) => assert_eq!(property, "powered"),
+        r => panic!("{:?}", r),
+    }
+    match furnace.resolve("facing=up".split(','), Some(&properties), MatchMode::Strict) {
+        Err(e @ MatchError::InvalidValue { .. }) => assert_eq!(e.to_string(), "`facing=up` is not one of north|south"),
+        r => panic!("{:?}", r),
+    }
+    assert!(furnace.resolve("facing=up".split(','), Some(&properties), MatchMode::Lenient).is_err());
+}
+
+#[test]
+fn test_state_key() {
+    let stairs = BlockStateKey::new("minecraft:oak_stairs", "waterlogged=true,half=top,facing=east,shape=straight".split(','));
+    assert_eq!(stairs.to_string(), "minecraft:oak_stairs[facing=east,half=top,shape=straight,waterlogged=true]");
+    assert_eq!(stairs.facing(), Some(Face::East));
+    assert_eq!(stairs.half(), Some(Half::Top));
+    assert!(stairs.waterlogged() && !stairs.lit());
+    assert_eq!(stairs.get("shape"), Some("straight"));
+    assert!(stairs.axis().is_none());
+
+    let wire = BlockStateKey::new("minecraft:redstone_wire", "power=13,bogus,north=side".split(','));
+    assert_eq!(wire.power(), Some(13));
+    assert_eq!(wire.properties.len(), 2);
+    // sorted the way blockstate files write their keys
+    assert_eq!(wire.key_iter().collect::<Vec<_>>(), vec!["north=side", "power=13"]);
+
+    let stone = BlockStateKey::new("minecraft:stone", "".split(','));
+    assert_eq!(stone.to_string(), "minecraft:stone");
+    assert_eq!(stone.level(), None);
+}
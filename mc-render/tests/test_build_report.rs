@@ -0,0 +1,208 @@
+use cgmath::Vector3;
+
+use mc_render::assets::data_type::Axis;
+use mc_render::assets::data_type::Rotate90;
+use mc_render::assets::data_raw::ModelRaw;
+use mc_render::assets::data_raw::BlockStateRaw;
+use mc_render::assets::data_raw::Rotation as RawRotation;
+use mc_render::model::ModelProvider;
+use mc_render::model::LazyModelProvider;
+use mc_render::model::model::BuildError;
+use mc_render::model::model::Rotation;
+use mc_render::model::random::JavaRandom;
+use mc_render::model::random::position_random;
+
+mod common;
+
+use common::JsonProvider;
+use common::NameTexGen;
+
+#[test]
+fn test_build_report() {
+    let mut bs_pvd: JsonProvider<BlockStateRaw> = JsonProvider::new(vec![
+        ("stone", r#"{"variants": {"": {"model": "block/stone"}}}"#),
+        ("orphan", r#"{"variants": {"": {"model": "block/orphan"}}}"#),
+        ("ghost", r#"{"variants": {"": {"model": "block/ghost"}}}"#),
+        ("air", r#"{"variants": {"": {"model": "block/air"}}}"#),
+    ]);
+    let mut mdl_pvd: JsonProvider<ModelRaw> = JsonProvider::new(vec![
+        ("block/stone", r##"{"textures": {"all": "block/stone"}, "elements": [{"faces": {"up": {"texture": "#all"}, "down": {"texture": "#side"}}}]}"##),
+        ("block/orphan", r#"{"parent": "block/lost", "textures": {}}"#),
+        ("block/air", r#"{"textures": {}}"#),
+    ]);
+    let mut tex_gen = NameTexGen;
+
+    let names = vec!["stone", "orphan", "ghost", "air", "missing"].into_iter().map(String::from);
+    let mut pvd = ModelProvider::new();
+    let report = pvd.build("minecraft", names, &mut bs_pvd, &mut mdl_pvd, &mut tex_gen);
+
+    assert!(!report.is_clean());
+    assert_eq!(report.failed.len(), 3);
+    let reason = |name: &str| report.failed.iter().find(|f| f.name == name).map(|f| f.error.clone());
+    match reason("minecraft:orphan") {
+        Some(BuildError::MissingParent { model, parent }) => {
+            assert_eq!(model, "block/orphan");
+            assert_eq!(parent, "block/lost");
+        },
+        e => panic!("{:?}", e),
+    }
+    match reason("minecraft:ghost") {
+        Some(BuildError::MissingModel { model }) => assert_eq!(model, "block/ghost"),
+        e => panic!("{:?}", e),
+    }
+    match reason("minecraft:missing") {
+        Some(BuildError::MissingBlockState) => { },
+        e => panic!("{:?}", e),
+    }
+    assert_eq!(report.unresolved_textures.len(), 1);
+    assert_eq!(report.unresolved_textures[0].reference, "#side");
+    assert_eq!(report.empty_models, vec!["block/air".to_string()]);
+    assert!(pvd.contains("minecraft:stone"));
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(json["failed"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn test_element_rotation() {
+    let mut bs_pvd: JsonProvider<BlockStateRaw> = JsonProvider::new(vec![
+        ("grass", r#"{"variants": {"": {"model": "block/cross"}}}"#),
+        ("skew", r#"{"variants": {"": {"model": "block/skew"}}}"#),
+    ]);
+    let mut mdl_pvd: JsonProvider<ModelRaw> = JsonProvider::new(vec![
+        ("block/cross", r##"{"textures": {"cross": "block/grass"}, "elements": [{"from": [0.8, 0, 8], "to": [15.2, 16, 8], "rotation": {"origin": [8, 8, 8], "axis": "y", "angle": 45, "rescale": true}, "faces": {"north": {"texture": "#cross"}}}]}"##),
+        ("block/skew", r##"{"textures": {"all": "block/stone"}, "elements": [{"rotation": {"axis": "x", "angle": 30}, "faces": {"up": {"texture": "#all"}}}]}"##),
+    ]);
+    let mut tex_gen = NameTexGen;
+    let names = vec!["grass", "skew"].into_iter().map(String::from);
+    let mut pvd = ModelProvider::new();
+    let report = pvd.build("minecraft", names, &mut bs_pvd, &mut mdl_pvd, &mut tex_gen);
+    assert!(pvd.contains("minecraft:grass"));
+    assert_eq!(report.failed.len(), 1);
+    match &report.failed[0].error {
+        BuildError::InvalidRotation { model, angle } => {
+            assert_eq!(model, "block/skew");
+            assert_eq!(*angle, 30.0);
+        },
+        e => panic!("{:?}", e),
+    }
+
+    // the rescaled diagonal reaches the corners the unrotated plane would reach on its axis
+    let raw = RawRotation { origin: [8.0, 8.0, 8.0], axis: Axis::Y, angle: 45.0, rescale: true };
+    let rotation = Rotation::from_raw(&raw);
+    let p = rotation.transf * Vector3::new(-7.2, 8.0, 0.0);
+    assert!((p.x.abs() - 7.2).abs() < 1e-4 && (p.z.abs() - 7.2).abs() < 1e-4);
+    assert!((p.y - 8.0).abs() < 1e-4);
+    let raw = RawRotation { rescale: false, .. raw };
+    let p = Rotation::from_raw(&raw).transf * Vector3::new(-7.2, 8.0, 0.0);
+    assert!((p.x.abs() - 7.2 / 2f32.sqrt()).abs() < 1e-4);
+}
+
+#[test]
+fn test_weighted_variants() {
+    assert_eq!(JavaRandom::new(0).next_long(), -4_962_768_465_676_381_896);
+    assert_eq!(JavaRandom::new(42).next_int(), -1_170_105_035);
+
+    let mut bs_pvd: JsonProvider<BlockStateRaw> = JsonProvider::new(vec![
+        ("stone", r#"{"variants": {"": [{"model": "block/stone", "weight": 3}, {"model": "block/stone", "y": 90}]}}"#),
+    ]);
+    let mut mdl_pvd: JsonProvider<ModelRaw> = JsonProvider::new(vec![
+        ("block/stone", r##"{"textures": {"all": "block/stone"}, "elements": [{"faces": {"up": {"texture": "#all"}}}]}"##),
+    ]);
+    let mut tex_gen = NameTexGen;
+    let mut pvd = ModelProvider::new();
+    let report = pvd.build("minecraft", vec!["stone".to_string()].into_iter(), &mut bs_pvd, &mut mdl_pvd, &mut tex_gen);
+    assert!(report.is_clean());
+    let models = pvd.get("minecraft:stone", "".split(','));
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].len(), 2);
+
+    let mut turned = 0;
+    for x in -32 .. 32 {
+        for z in -32 .. 32 {
+            let loc = Vector3::new(x, 64, z);
+            let picked = pvd.get_at("minecraft:stone", "".split(','), &loc);
+            assert_eq!(picked.len(), 1);
+            assert!(std::rc::Rc::ptr_eq(&picked[0], &pvd.get_at("minecraft:stone", "".split(','), &loc)[0]));
+            if picked[0].y == Rotate90::R90 {
+                turned += 1;
+            }
+        }
+    }
+    // one in four, give or take
+    assert!(turned > 4096 / 4 - 200 && turned < 4096 / 4 + 200, "{}", turned);
+}
+
+#[test]
+fn test_weighted_multipart() {
+    let mut bs_pvd: JsonProvider<BlockStateRaw> = JsonProvider::new(vec![
+        ("stone", r#"{"variants": {"": [{"model": "block/stone"}, {"model": "block/stone", "y": 90}]}}"#),
+        ("pile", r#"{"multipart": [
+            {"apply": [{"model": "block/stone"}, {"model": "block/stone", "y": 90}]},
+            {"apply": [{"model": "block/stone"}, {"model": "block/stone", "y": 90}]}
+        ]}"#),
+    ]);
+    let mut mdl_pvd: JsonProvider<ModelRaw> = JsonProvider::new(vec![
+        ("block/stone", r##"{"textures": {"all": "block/stone"}, "elements": [{"faces": {"up": {"texture": "#all"}}}]}"##),
+    ]);
+    let mut tex_gen = NameTexGen;
+    let mut pvd = ModelProvider::new();
+    let report = pvd.build("minecraft", vec!["stone".to_string(), "pile".to_string()].into_iter(), &mut bs_pvd, &mut mdl_pvd, &mut tex_gen);
+    assert!(report.is_clean());
+    assert!(pvd.get("minecraft:pile", "".split(',')).iter().all(|weighted| weighted.is_part()));
+
+    let mut differ = 0;
+    for x in -16 .. 16 {
+        for z in -16 .. 16 {
+            let loc = Vector3::new(x, 64, z);
+            // vanilla `MultipartBakedModel`: every case picks with `new Random(rand.nextLong())`
+            let k = JavaRandom::new(position_random(&loc)).next_long();
+            let turned = (JavaRandom::new(k).next_long() as i32).wrapping_abs() % 2 == 1;
+            let picked = pvd.get_at("minecraft:pile", "".split(','), &loc);
+            assert_eq!(picked.len(), 2);
+            for tmodel in picked.iter() {
+                assert_eq!(tmodel.y == Rotate90::R90, turned);
+            }
+            if pvd.get_at("minecraft:stone", "".split(','), &loc)[0].y != picked[0].y {
+                differ += 1;
+            }
+        }
+    }
+    assert!(differ > 0);
+}
+
+#[test]
+fn test_lazy_build() {
+    let bs_pvd: JsonProvider<BlockStateRaw> = JsonProvider::new(vec![
+        ("stone", r#"{"variants": {"": {"model": "block/stone"}}}"#),
+        ("dirt", r#"{"variants": {"": {"model": "block/dirt"}}}"#),
+        ("ghost", r#"{"variants": {"": {"model": "block/ghost"}}}"#),
+        ("smooth_stone", r#"{"variants": {"": {"model": "block/stone", "y": 90}}}"#),
+    ]);
+    let mdl_pvd: JsonProvider<ModelRaw> = JsonProvider::new(vec![
+        ("block/stone", r##"{"textures": {"all": "block/stone"}, "elements": [{"faces": {"up": {"texture": "#all"}}}]}"##),
+        ("block/dirt", r##"{"textures": {"all": "block/dirt"}, "elements": [{"faces": {"up": {"texture": "#all"}}}]}"##),
+    ]);
+    let lazy = LazyModelProvider::new("minecraft", bs_pvd, mdl_pvd, NameTexGen);
+    assert!(lazy.is_empty());
+
+    let stone = lazy.get("minecraft:stone", "".split(','));
+    assert_eq!(stone.len(), 1);
+    assert_eq!(stone[0].first().unwrap().model.elements[0].faces.iter().flatten().next().unwrap().texture, "block/stone");
+    assert_eq!(lazy.get("minecraft:stone", "".split(',')).len(), 1);
+    assert!(!lazy.load("minecraft:ghost"));
+    assert!(!lazy.load("minecraft:ghost"));
+    assert!(!lazy.load("other:stone"));
+    assert_eq!(lazy.len(), 1);
+    // built by the same builder, the model is shared
+    let smooth = lazy.get("minecraft:smooth_stone", "".split(','));
+    assert!(std::rc::Rc::ptr_eq(&stone[0].first().unwrap().model, &smooth[0].first().unwrap().model));
+    assert_eq!(lazy.len(), 2);
+
+    let (pvd, _, report) = lazy.into_inner();
+    assert!(pvd.contains("minecraft:stone") && !pvd.contains("minecraft:dirt"));
+    assert!(pvd.placeholder().is_some());
+    // tried once, reported once
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].name, "minecraft:ghost");
+}
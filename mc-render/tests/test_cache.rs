@@ -0,0 +1,100 @@
+use std::io::Write;
+use std::rc::Rc;
+
+use mc_render::assets::data_raw::ModelRaw;
+use mc_render::assets::data_raw::BlockStateRaw;
+use mc_render::model::ModelProvider;
+use mc_render::model::cache;
+use mc_render::model::cache::Binary;
+use mc_render::glrender::texture::TextureSet;
+
+mod common;
+
+use common::JsonProvider;
+use common::NameTexGen;
+
+fn build() -> ModelProvider<String> {
+    let mut bs_pvd: JsonProvider<BlockStateRaw> = JsonProvider::new(vec![
+        ("stone", r#"{"variants": {"": [{"model": "block/stone"}, {"model": "block/stone", "y": 90, "weight": 3}]}}"#),
+        ("log", r#"{"variants": {"axis=x": {"model": "block/stone", "x": 90, "y": 90, "uvlock": true}, "axis=y": {"model": "block/stone"}}}"#),
+        ("fence", r#"{"multipart": [{"apply": {"model": "block/post"}}, {"when": {"OR": [{"north": "true"}, {"east": "true|false"}]}, "apply": {"model": "block/stone"}}]}"#),
+    ]);
+    let mut mdl_pvd: JsonProvider<ModelRaw> = JsonProvider::new(vec![
+        ("block/stone", r##"{"textures": {"all": "block/stone"}, "display": {"gui": {"rotation": [30, 225, 0], "scale": [0.625, 0.625, 0.625]}}, "elements": [{"faces": {"up": {"texture": "#all", "cullface": "up"}, "north": {"texture": "#all", "rotation": 90, "tintindex": 0}}}]}"##),
+        ("block/post", r##"{"textures": {"post": "block/planks"}, "elements": [{"from": [6, 0, 6], "to": [10, 16, 10], "rotation": {"origin": [8, 8, 8], "axis": "y", "angle": 45}, "faces": {"up": {"texture": "#post", "uv": [6, 6, 10, 10]}}}]}"##),
+    ]);
+    let mut pvd = ModelProvider::new();
+    pvd.build("minecraft", vec!["stone", "log", "fence"].into_iter().map(String::from), &mut bs_pvd, &mut mdl_pvd, &mut NameTexGen);
+    pvd
+}
+
+#[test]
+fn test_cache_round_trip() {
+    let pvd = build();
+    let mut data = Vec::new();
+    cache::save(&mut data, 42, &pvd).unwrap();
+    let loaded: ModelProvider<String> = cache::load(&mut data.as_slice(), 42).unwrap().unwrap();
+
+    let mut names: Vec<&str> = loaded.names().collect();
+    names.sort_unstable();
+    assert_eq!(names, vec!["minecraft:fence", "minecraft:log", "minecraft:stone"]);
+    for (name, key) in [("minecraft:stone", ""), ("minecraft:log", "axis=x"), ("minecraft:log", "axis=y"), ("minecraft:fence", "north=true")].iter() {
+        let expected = format!("{:?}", pvd.get(name, key.split(',')));
+        assert_eq!(format!("{:?}", loaded.get(name, key.split(','))), expected);
+    }
+    // both weighted stone variants still share one model
+    let stone = loaded.get("minecraft:stone", "".split(','));
+    assert_eq!(stone.len(), 1);
+    let variants: Vec<_> = stone[0].entries().collect();
+    assert_eq!(variants.iter().map(|(_, w)| *w).collect::<Vec<_>>(), vec![1, 3]);
+    assert!(Rc::ptr_eq(&variants[0].0.model, &variants[1].0.model));
+    assert_eq!(loaded.get("minecraft:fence", "north=true".split(',')).len(), 2);
+
+    // another fingerprint is a stale cache, not an error
+    assert!(cache::load::<ModelProvider<String>>(&mut data.as_slice(), 43).unwrap().is_none());
+    assert!(cache::load::<ModelProvider<String>>(&mut &b"not a cache"[..], 42).unwrap().is_none());
+}
+
+#[test]
+fn test_texture_set_size() {
+    let set = TextureSet { names: vec!["stone".to_string()], images: vec![image::RgbaImage::new(2, 3)] };
+    let mut data = Vec::new();
+    set.write_to(&mut data).unwrap();
+    let loaded = TextureSet::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(loaded.images[0].dimensions(), (2, 3));
+
+    // a corrupt size is an error, not a huge allocation
+    let mut data = Vec::new();
+    vec!["stone".to_string()].write_to(&mut data).unwrap();
+    1u32.write_to(&mut data).unwrap();
+    0x10000u32.write_to(&mut data).unwrap();
+    0x10000u32.write_to(&mut data).unwrap();
+    let err = TextureSet::read_from(&mut data.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+fn write_zip(path: &std::path::Path, entries: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    for (name, data) in entries {
+        zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+#[test]
+fn test_fingerprint_contents() {
+    let dir = std::env::temp_dir().join(format!("mc-render-fingerprint-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("assets.jar");
+    let fingerprint = |entries: &[(&str, &[u8])]| {
+        write_zip(&path, entries);
+        cache::fingerprint(&[&path]).unwrap()
+    };
+    let stone = fingerprint(&[("assets/minecraft/models/block/stone.json", b"{\"parent\": \"block/cube_all\"}"), ("a/Main.class", b"1")]);
+    // rewritten, other classes: the same assets
+    assert_eq!(stone, fingerprint(&[("assets/minecraft/models/block/stone.json", b"{\"parent\": \"block/cube_all\"}"), ("a/Main.class", b"2")]));
+    // same size, other contents
+    assert_ne!(stone, fingerprint(&[("assets/minecraft/models/block/stone.json", b"{\"parent\": \"block/cube_mud\"}"), ("a/Main.class", b"1")]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
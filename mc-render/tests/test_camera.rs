@@ -0,0 +1,119 @@
+use cgmath::Deg;
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::Vector4;
+use cgmath::Matrix4;
+
+use mc_render::assets::data_type::Face;
+use mc_render::assets::data_raw::Transform;
+use mc_render::assets::data_raw::DisplayRaw;
+use mc_render::model::model::Display;
+use mc_render::model::model::DisplayContext;
+use mc_render::glrender::camera::Camera;
+use mc_render::model::block::BlockBounds;
+use mc_render::model::block::HeightRange;
+
+fn project(camera: &Camera, loc: Vector3<i32>) -> Vector4<f32> {
+    let p = camera.matrix() * (loc - camera.center()).cast::<f32>().unwrap().extend(1.0);
+    p / p.w
+}
+
+fn assert_near(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+}
+
+#[test]
+fn test_top_down_tile() {
+    let height = HeightRange::EXTENDED;
+    let camera = Camera::top_down(BlockBounds::columns(256, 0, 256, 256, &height), 1.0);
+    assert_eq!(camera.center(), Vector3::new(384, height.center(), 128));
+
+    let old = Matrix4::from_angle_x(Deg(90.0)) * Matrix4::from_nonuniform_scale(1.0 / 128.0, 2.0 / height.span() as f32, 1.0 / 128.0);
+    let m = camera.matrix();
+    for (a, b) in [m.x, m.y, m.z, m.w].iter().zip([old.x, old.y, old.z, old.w].iter()) {
+        for i in 0 .. 4 {
+            assert_near(a[i], b[i]);
+        }
+    }
+    assert!(camera.contains(&Vector3::new(256, -64, 0)));
+    assert!(!camera.contains(&Vector3::new(512, 0, 0)));
+}
+
+#[test]
+fn test_isometric_fits_bounds() {
+    let bounds = BlockBounds::new(Vector3::new(0, 0, 0), Vector3::new(16, 16, 16));
+    let camera = Camera::isometric(bounds, 1.0);
+    for &x in &[0, 16] {
+        for &y in &[0, 16] {
+            for &z in &[0, 16] {
+                let p = project(&camera, Vector3::new(x, y, z));
+                assert!(p.x.abs() <= 1.0001 && p.y.abs() <= 1.0001 && p.z.abs() <= 1.0001);
+            }
+        }
+    }
+    let low = project(&camera, Vector3::new(8, 0, 8));
+    let high = project(&camera, Vector3::new(8, 16, 8));
+    assert!(high.y > low.y);
+    assert!(high.z > low.z);
+    // the south-east corner faces the camera
+    assert!(project(&camera, Vector3::new(16, 8, 16)).z > project(&camera, Vector3::new(0, 8, 0)).z);
+}
+
+#[test]
+fn test_perspective_bounds() {
+    let eye = Point3::new(0.5, 100.0, 0.5);
+    let target = Point3::new(0.5, 60.0, -40.0);
+    let camera = Camera::perspective(eye, target, Deg(60.0), 1.0, 128.0);
+    assert_eq!(camera.center(), Vector3::new(0, 100, 0));
+    assert!(camera.contains(&Vector3::new(0, 60, -40)));
+    assert!(!camera.contains(&Vector3::new(0, 60, 40)));
+
+    let p = project(&camera, Vector3::new(0, 60, -40));
+    assert!(p.x.abs() < 0.1 && p.y.abs() < 0.1);
+    let near = project(&camera, Vector3::new(0, 80, -20));
+    assert!(near.z > p.z);
+
+    let down = Camera::perspective(eye, Point3::new(0.5, 0.0, 0.5), Deg(60.0), 1.0, 128.0);
+    let p = project(&down, Vector3::new(0, 64, -8));
+    assert!(p.y > 0.0);
+}
+
+#[test]
+fn test_gui_block() {
+    // `display.gui` of vanilla `block/block.json`
+    let raw = DisplayRaw {
+        gui: Some(Transform { rotation: [30.0, 225.0, 0.0], translation: [0.0, 0.0, 0.0], scale: [0.625, 0.625, 0.625] }),
+        .. Default::default()
+    };
+    let display = Display::from_raw(&raw);
+    assert!(display.get(DisplayContext::Gui).is_some());
+    assert!(display.get(DisplayContext::Head).is_none());
+    let center = display.transform(DisplayContext::Head) * Vector4::new(0.5, 0.5, 0.5, 1.0);
+    assert_near(center.x.abs() + center.y.abs() + center.z.abs(), 0.0);
+
+    let camera = Camera::display(display.transform(DisplayContext::Gui), 1.0);
+    let mut extent: f32 = 0.0;
+    for &x in &[0, 1] {
+        for &y in &[0, 1] {
+            for &z in &[0, 1] {
+                let p = project(&camera, Vector3::new(x, y, z));
+                assert!(p.z.abs() <= 1.0);
+                extent = extent.max(p.x.abs()).max(p.y.abs());
+            }
+        }
+    }
+    assert!(extent > 0.9 && extent <= 1.0);
+    // the top is seen, the bottom is not
+    assert!(project(&camera, Vector3::new(0, 1, 0)).z > project(&camera, Vector3::new(0, 0, 0)).z);
+    assert!(project(&camera, Vector3::new(1, 1, 1)).y > project(&camera, Vector3::new(1, 0, 1)).y);
+}
+
+#[test]
+fn test_camera_faces() {
+    let bounds = BlockBounds::new(Vector3::new(0, 0, 0), Vector3::new(16, 16, 16));
+    assert_eq!(Camera::top_down(bounds, 1.0).faces(), vec![Face::Up]);
+    // from the south-east and above
+    assert_eq!(Camera::isometric(bounds, 1.0).faces(), vec![Face::South, Face::Up, Face::East]);
+    let eye = Point3::new(0.0, 80.0, 0.0);
+    assert_eq!(Camera::perspective(eye, Point3::new(10.0, 64.0, 10.0), Deg(60.0), 1.0, 64.0).faces().len(), 6);
+}
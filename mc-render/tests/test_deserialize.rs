@@ -17,6 +17,29 @@ fn test_raw() {
     
 }
 
+#[test]
+fn test_merge_inherits_textures() {
+    use mc_render::assets::data_raw::*;
+    let mut child: ModelRaw = serde_json::from_str(r##"{
+        "parent": "block/cube_all",
+        "display": { "gui": { "rotation": [30, 45, 0] } }
+    }"##).unwrap();
+    let parent: ModelRaw = serde_json::from_str(r##"{
+        "parent": "block/cube",
+        "display": { "gui": { "rotation": [0, 0, 0] }, "head": { "scale": [2, 2, 2] } },
+        "textures": { "all": "block/stone", "particle": "#all" }
+    }"##).unwrap();
+    child.merge(&parent);
+    assert_eq!(child.parent.as_deref(), Some("block/cube"));
+    // a child without textures takes those of the parent, and keeps its own display
+    let textures = child.textures.as_ref().unwrap();
+    assert_eq!(textures.get("all").map(String::as_str), Some("block/stone"));
+    assert_eq!(textures.get("particle").map(String::as_str), Some("#all"));
+    let display = child.display.as_ref().unwrap();
+    assert_eq!(display.gui.as_ref().map(|t| t.rotation), Some([30.0, 45.0, 0.0]));
+    assert!(display.head.is_some());
+}
+
 #[test]
 fn test_merge_raw() {
     use mc_render::assets::data_raw::*;
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use cgmath::Vector3;
+
+use mc_render::assets::data_type::Face;
+use mc_render::model::model::RenderLayer;
+use mc_render::model::blockstate::Weighted;
+use mc_render::model::fluid;
+use mc_render::model::fluid::Fluid;
+use mc_render::model::fluid::FluidTexture;
+use mc_render::model::fluid::FluidTextures;
+
+mod common;
+
+use common::Voxels;
+use common::Quad;
+use common::Quads;
+use common::block_model;
+
+fn textures() -> FluidTextures<&'static str> {
+    FluidTextures {
+        water: FluidTexture { still: "water_still", flow: "water_flow", layer: RenderLayer::Translucent, tinted: true },
+        lava: FluidTexture { still: "lava_still", flow: "lava_flow", layer: RenderLayer::Solid, tinted: false },
+    }
+}
+
+fn mesh(world: &Voxels, loc: (i32, i32, i32)) -> Vec<Quad> {
+    let mut quads = Quads::default();
+    fluid::draw(&Face::ALL, &Vector3::new(loc.0, loc.1, loc.2), &mut quads, world, &textures()).unwrap();
+    quads.0
+}
+
+fn top(quads: &[Quad]) -> &Quad {
+    quads.iter().find(|q| q.pos.iter().all(|p| p.y > 0.0)).unwrap()
+}
+
+#[test]
+fn test_fluid_source() {
+    let mut fluids = HashMap::new();
+    fluids.insert((0, 1, 0), (Fluid::Water, 0));
+    let world = Voxels { fluids, .. Default::default() };
+    let quads = mesh(&world, (0, 1, 0));
+    assert_eq!(quads.len(), 6);
+    // a lone source: ten parts of 8/9 against three of air at every corner
+    let top = top(&quads);
+    for p in top.pos.iter() {
+        assert!((p.y - 16.0 * (80.0 / 9.0) / 13.0).abs() < 1e-4, "{}", p.y);
+    }
+    assert_eq!(top.tex, "water_still");
+    assert_eq!(top.color, [63, 118, 228, 255]);
+    assert_eq!(fluid::level_height(0), fluid::level_height(8));
+    assert!(fluid::level_height(7) < fluid::level_height(1));
+}
+
+#[test]
+fn test_fluid_culling_and_slope() {
+    let mut fluids = HashMap::new();
+    fluids.insert((0, 1, 0), (Fluid::Lava, 0));
+    fluids.insert((1, 1, 0), (Fluid::Lava, 6));
+    fluids.insert((0, 2, 0), (Fluid::Lava, 0));
+    let mut blocks = HashMap::new();
+    blocks.insert((0, 0, 0), 0);
+    let world = Voxels { blocks, models: vec![vec![Weighted::from(block_model("stone", 16.0))]], fluids };
+
+    // under more lava: no top and no bottom against stone, no side towards the other lava
+    let quads = mesh(&world, (0, 1, 0));
+    assert_eq!(quads.len(), 3);
+    assert!(quads.iter().all(|q| q.tex == "lava_flow" && q.color == [255; 4]));
+    assert!(quads.iter().all(|q| q.pos.iter().any(|p| p.y == 16.0)));
+
+    // the shallow neighbour slopes down to the east, away from the full block
+    let quads = mesh(&world, (1, 1, 0));
+    let top = top(&quads);
+    assert_eq!(top.tex, "lava_flow");
+    let west = top.pos.iter().filter(|p| p.x == 0.0).map(|p| p.y).fold(0.0, f32::max);
+    let east = top.pos.iter().filter(|p| p.x == 16.0).map(|p| p.y).fold(16.0, f32::min);
+    assert!(west == 16.0 && east < 16.0 * fluid::level_height(0), "{} {}", west, east);
+}
@@ -0,0 +1,43 @@
+use image::Rgba;
+use image::RgbaImage;
+
+use mc_render::glrender::glow::Glow;
+
+#[test]
+fn test_glow_spreads_and_adds() {
+    let mut image = RgbaImage::from_pixel(15, 15, Rgba([100, 100, 100, 255]));
+    image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+    let mut mask = RgbaImage::from_pixel(15, 15, Rgba([0, 0, 0, 255]));
+    for x in 6..9 {
+        for y in 6..9 {
+            mask.put_pixel(x, y, Rgba([255, 200, 0, 255]));
+        }
+    }
+    let glow = Glow { radius: 6, .. Default::default() };
+    glow.apply(&mut image, &mask);
+
+    // brightest at the source, fading out, never darker than before
+    let centre = image.get_pixel(7, 7);
+    let near = image.get_pixel(10, 7);
+    let far = image.get_pixel(12, 7);
+    assert!(centre[0] > near[0] && near[0] > far[0] && far[0] > 100, "{:?} {:?} {:?}", centre, near, far);
+    assert!(centre[0] > centre[1] && centre[2] == 100);
+    assert_eq!(image.get_pixel(14, 14), &Rgba([100, 100, 100, 255]));
+    // out of reach, the transparent corner stays transparent
+    assert_eq!(image.get_pixel(0, 0)[3], 0);
+}
+
+#[test]
+fn test_glow_strength() {
+    let mask = RgbaImage::from_pixel(4, 4, Rgba([40, 40, 40, 255]));
+    let mut image = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+    let glow = Glow { radius: 3, strength: 0.0, .. Default::default() };
+    glow.apply(&mut image, &mask);
+    assert_eq!(image.get_pixel(1, 1), &Rgba([0, 0, 0, 0]));
+
+    let glow = Glow { radius: 3, strength: 2.0, .. Default::default() };
+    glow.apply(&mut image, &mask);
+    // glow over nothing is the light itself, as opaque as it is bright
+    let p = image.get_pixel(1, 1);
+    assert!(p[0] == 255 && p[3] > 0 && p[3] < 80, "{:?}", p);
+}
@@ -0,0 +1,82 @@
+use image::Rgba;
+use image::RgbaImage;
+
+use cgmath::Vector2;
+use cgmath::Vector3;
+use cgmath::Vector4;
+use cgmath::Matrix4;
+
+use mc_render::model::BlockRenderer;
+use mc_render::model::model::RenderLayer;
+use mc_render::glrender::MeshGenerator;
+use mc_render::glrender::sort_translucent;
+use mc_render::glrender::texture::CombinedTexture;
+use mc_render::glrender::mesh::Mesh;
+use mc_render::glrender::mesh::MeshVertex;
+use mc_render::glrender::texture::texture_layer;
+
+fn quad(y: i32) -> [MeshVertex; 4] {
+    let v = MeshVertex { loc: [0, y, 0], pos: [0.0; 3], tex: [0.0; 2], tex_id: 0, color: [255; 4], light: 0 };
+    [v, v, v, v]
+}
+
+#[test]
+fn test_sorted_indices() {
+    let mut mesh = Mesh::new();
+    for y in &[5, 1, 3] {
+        mesh.append(&quad(*y));
+    }
+    let indices = mesh.sorted_indices(|q| q[0].loc[1] as f32);
+    assert_eq!(indices, vec![4, 5, 6, 7, 6, 5, 8, 9, 10, 11, 10, 9, 0, 1, 2, 3, 2, 1]);
+}
+
+#[test]
+fn test_sort_translucent_across_meshes() {
+    let mut a = Mesh::new();
+    a.append(&quad(5));
+    a.append(&quad(1));
+    a.set_layer(RenderLayer::Translucent);
+    let mut b = Mesh::new();
+    b.append(&quad(3));
+    b.set_layer(RenderLayer::Translucent);
+    let mut solid = Mesh::new();
+    solid.append(&quad(2));
+    // depth is y with this matrix, so back to front is ascending y
+    let y_depth = Matrix4::from_cols(Vector4::unit_x(), Vector4::unit_z(), Vector4::unit_y(), Vector4::unit_w());
+    let sorted = sort_translucent(&[&a, &solid, &b], y_depth, Vector3::new(0, 0, 0));
+    assert_eq!(sorted.len(), 1);
+    assert_eq!(sorted[0].layer(), RenderLayer::Translucent);
+    let ys: Vec<_> = sorted[0].quads().map(|q| q[0].loc[1]).collect();
+    assert_eq!(ys, vec![1, 3, 5]);
+}
+
+#[test]
+fn test_texture_layer() {
+    let mut image = RgbaImage::from_pixel(16, 16, Rgba([255; 4]));
+    assert_eq!(texture_layer("block/stone", &image), RenderLayer::Solid);
+    image.put_pixel(3, 3, Rgba([0; 4]));
+    assert_eq!(texture_layer("block/glass", &image), RenderLayer::Cutout);
+    assert_eq!(texture_layer("block/oak_leaves", &image), RenderLayer::CutoutMipped);
+    image.put_pixel(4, 4, Rgba([255, 255, 255, 128]));
+    assert_eq!(texture_layer("block/ice", &image), RenderLayer::Translucent);
+    assert_eq!(RenderLayer::from_priority(RenderLayer::Translucent.priority()), RenderLayer::Translucent);
+}
+
+#[test]
+fn test_mesh_generator_splits_full_meshes() {
+    let mut r = MeshGenerator::new();
+    let (v, t) = (Vector3::new(0.0, 0.0, 0.0), Vector2::new(0.0, 0.0));
+    let quad = |r: &mut MeshGenerator, prior: i32| {
+        r.state(prior);
+        r.draw(Vector3::new(0, 0, 0), v, v, v, v, t, t, t, t, CombinedTexture(0), [255; 4], 0).unwrap();
+    };
+    for _ in 0 .. 1 << 14 {
+        quad(&mut r, 0);
+    }
+    // a full solid mesh, then back and forth with the translucent one
+    for &prior in &[3, 0, 3, 0] {
+        quad(&mut r, prior);
+    }
+    let sizes: Vec<_> = r.unwrap().iter().map(|m| (m.layer(), m.size().0)).collect();
+    assert_eq!(sizes, vec![(RenderLayer::Solid, 1 << 16), (RenderLayer::Solid, 8), (RenderLayer::Translucent, 8)]);
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use cgmath::Vector3;
+
+use mc_render::model;
+use mc_render::model::block::BlockBounds;
+use mc_render::model::blockstate::Weighted;
+use mc_render::model::model::RenderLayer;
+use mc_render::glrender::camera::Camera;
+
+mod common;
+
+use common::Voxels;
+use common::Quads;
+use common::block_model;
+
+fn section() -> BlockBounds {
+    BlockBounds::new(Vector3::new(0, 0, 0), Vector3::new(16, 16, 16))
+}
+
+#[test]
+fn test_draw_section_culls_interior() {
+    let mut blocks = HashMap::new();
+    for y in 0 .. 3 {
+        for z in 0 .. 3 {
+            for x in 0 .. 3 {
+                blocks.insert((x + 4, y + 4, z + 4), 0);
+            }
+        }
+    }
+    blocks.insert((0, 0, 0), 0);
+    let world = Voxels { blocks, models: vec![vec![Weighted::from(block_model("stone", 16.0))]], .. Default::default() };
+    let mut counter = Quads::default();
+    model::draw_section(&Vector3::new(0, 0, 0), &section(), &mut counter, &world, None).unwrap();
+    // 3x3x3 cube shows 9 faces per side, nothing culls the lone block, not even the bottom of the world
+    assert_eq!(counter.0.len(), 9 * 6 + 6);
+}
+
+#[test]
+fn test_occlusion_partial_and_transparent() {
+    let slab = block_model("stone", 8.0);
+    let glass = block_model("glass", 16.0);
+    assert_eq!(slab.model.occlusion, [false, true, false, false, false, false]);
+    assert_eq!(glass.model.occlusion, [false; 6]);
+    assert_eq!(slab.model.layer, RenderLayer::Solid);
+    assert_eq!(glass.model.layer, RenderLayer::Cutout);
+
+    let mut blocks = HashMap::new();
+    blocks.insert((0, 1, 0), 0);
+    blocks.insert((1, 1, 0), 1);
+    blocks.insert((0, 1, 1), 2);
+    let world = Voxels { blocks, models: vec![vec![Weighted::from(block_model("stone", 16.0))], vec![Weighted::from(slab)], vec![Weighted::from(glass)]], .. Default::default() };
+    let mut counter = Quads::default();
+    model::draw_section(&Vector3::new(0, 0, 0), &section(), &mut counter, &world, None).unwrap();
+    // stone is seen through the glass and above the slab; the slab and the glass both lose the face against the stone
+    assert_eq!(counter.0.len(), 6 + 5 + 5);
+}
+
+#[test]
+fn test_draw_section_bounds() {
+    let mut blocks = HashMap::new();
+    blocks.insert((1, 1, 1), 0);
+    blocks.insert((20, 1, 1), 0);
+    blocks.insert((1, 40, 1), 0);
+    let world = Voxels { blocks, models: vec![vec![Weighted::from(block_model("stone", 16.0))]], .. Default::default() };
+    let mut counter = Quads::default();
+    // only the block inside both the section and the bounds
+    let bounds = BlockBounds::new(Vector3::new(0, 0, 0), Vector3::new(32, 32, 32));
+    model::draw_section(&Vector3::new(0, 0, 0), &bounds, &mut counter, &world, None).unwrap();
+    assert_eq!(counter.0.len(), 6);
+    let bounds = BlockBounds::new(Vector3::new(2, 0, 0), Vector3::new(32, 32, 32));
+    let mut counter = Quads::default();
+    model::draw_section(&Vector3::new(0, 0, 0), &bounds, &mut counter, &world, None).unwrap();
+    assert_eq!(counter.0.len(), 0);
+}
+
+#[test]
+fn test_camera_faces_mesh_sides() {
+    // two blocks side by side
+    let mut blocks = HashMap::new();
+    blocks.insert((0, 0, 0), 0);
+    blocks.insert((1, 0, 0), 0);
+    let world = Voxels { blocks, models: vec![vec![Weighted::from(block_model("stone", 16.0))]], .. Default::default() };
+    let bounds = BlockBounds::new(Vector3::new(0, 0, 0), Vector3::new(16, 16, 16));
+    let count = |camera: &Camera| {
+        let mut counter = Quads::default();
+        for loc in &[Vector3::new(0, 0, 0), Vector3::new(1, 0, 0)] {
+            model::draw(&camera.faces(), loc, &mut counter, &world).unwrap();
+        }
+        counter.0.len()
+    };
+    assert_eq!(count(&Camera::top_down(bounds, 1.0)), 2);
+    // up, south and east of both, but the east face of the western block is culled
+    assert_eq!(count(&Camera::isometric(bounds, 1.0)), 5);
+}
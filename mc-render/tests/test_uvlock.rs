@@ -0,0 +1,90 @@
+use std::rc::Rc;
+
+use cgmath::Deg;
+use cgmath::InnerSpace;
+use cgmath::Matrix2;
+use cgmath::Matrix3;
+use cgmath::SquareMatrix;
+
+use mc_render::assets::data_type::Face;
+use mc_render::assets::data_type::Rotate90;
+use mc_render::model::model::Model;
+use mc_render::model::model::FaceTexture;
+use mc_render::model::model::RenderLayer;
+use mc_render::model::model::TransformedModel;
+
+fn transformed(x: Rotate90, y: Rotate90) -> TransformedModel<()> {
+    let model = Model {
+        ambientocclusion: true,
+        elements: Vec::new(),
+        occlusion: [false; 6],
+        layer: RenderLayer::Solid,
+        display: Default::default(),
+    };
+    TransformedModel::from_mxy(Rc::new(model), x, y, true)
+}
+
+fn face(uv: [f32; 4], rotation: Rotate90) -> FaceTexture<()> {
+    FaceTexture {
+        uv: Matrix2::new(uv[0], uv[1], uv[2], uv[3]),
+        cullface: None,
+        rotation,
+        texture: (),
+        tintindex: None,
+    }
+}
+
+fn lock(x: Rotate90, y: Rotate90, original_face: Face, uv: [f32; 4]) -> ([f32; 4], Rotate90) {
+    let (uv, rotation) = transformed(x, y).face_uv(original_face, &face(uv, Rotate90::R0));
+    let round = |v: f32| (v * 1000.0).round() / 1000.0;
+    ([round(uv.x.x), round(uv.x.y), round(uv.y.x), round(uv.y.y)], rotation)
+}
+
+#[test]
+fn test_uvlock_identity() {
+    for f in Face::ALL.iter() {
+        assert_eq!(lock(Rotate90::R0, Rotate90::R0, f.clone(), [2.0, 4.0, 10.0, 12.0]), ([2.0, 4.0, 10.0, 12.0], Rotate90::R0));
+    }
+}
+
+#[test]
+fn test_uvlock_y() {
+    // top turns against the model, sides are already aligned
+    assert_eq!(lock(Rotate90::R0, Rotate90::R90, Face::Up, [0.0, 0.0, 16.0, 16.0]), ([0.0, 0.0, 16.0, 16.0], Rotate90::R270));
+    assert_eq!(lock(Rotate90::R0, Rotate90::R90, Face::North, [0.0, 8.0, 16.0, 16.0]), ([0.0, 8.0, 16.0, 16.0], Rotate90::R0));
+    // half of a stair top moves to the other axis
+    assert_eq!(lock(Rotate90::R0, Rotate90::R90, Face::Up, [0.0, 0.0, 16.0, 8.0]), ([8.0, 0.0, 16.0, 16.0], Rotate90::R270));
+    assert_eq!(lock(Rotate90::R0, Rotate90::R180, Face::Up, [0.0, 0.0, 16.0, 8.0]), ([0.0, 8.0, 16.0, 16.0], Rotate90::R180));
+}
+
+#[test]
+fn test_rotation_vanilla() {
+    // vanilla `ModelRotation`: Ry(-y) * Rx(-x)
+    let r = [Rotate90::R0, Rotate90::R90, Rotate90::R180, Rotate90::R270];
+    for x in r.iter() {
+        for y in r.iter() {
+            let vanilla = Matrix3::from_angle_y(Deg(-90.0 * y.index() as f32)) * Matrix3::from_angle_x(Deg(-90.0 * x.index() as f32));
+            let m = transformed(x.clone(), y.clone()).mapping_transform(&Matrix3::identity());
+            for (a, b) in [m.x, m.y, m.z].iter().zip([vanilla.x, vanilla.y, vanilla.z].iter()) {
+                assert!((a - b).magnitude() < 1e-5, "x={:?} y={:?}: {:?} != {:?}", x, y, m, vanilla);
+            }
+        }
+    }
+    // observer `facing=down` is x=90 and `facing=up` x=270, its face is on the north side of the model
+    assert_eq!(transformed(Rotate90::R90, Rotate90::R0).inv_mapping(Face::North), Face::Down);
+    assert_eq!(transformed(Rotate90::R270, Rotate90::R0).inv_mapping(Face::North), Face::Up);
+    assert_eq!(transformed(Rotate90::R90, Rotate90::R0).inv_mapping(Face::Up), Face::North);
+}
+
+#[test]
+fn test_uvlock_x() {
+    // worked out with vanilla `UVTransformationUtil.getUVLockTransform` and `FaceBakery.applyUVLock`:
+    // the sides turn with x=90, east a quarter back and west a quarter forward
+    assert_eq!(lock(Rotate90::R90, Rotate90::R0, Face::East, [0.0, 0.0, 16.0, 16.0]).1, Rotate90::R270);
+    assert_eq!(lock(Rotate90::R90, Rotate90::R0, Face::West, [0.0, 0.0, 16.0, 16.0]).1, Rotate90::R90);
+    // the north side of a log lying along z ends up at the bottom, it needs a half turn to stay aligned
+    assert_eq!(lock(Rotate90::R90, Rotate90::R0, Face::North, [0.0, 0.0, 16.0, 8.0]), ([0.0, 8.0, 16.0, 16.0], Rotate90::R180));
+    assert_eq!(lock(Rotate90::R90, Rotate90::R0, Face::South, [0.0, 0.0, 16.0, 16.0]).1, Rotate90::R0);
+    // upside down stairs: the lower half of the south side becomes the upper half of the north side
+    assert_eq!(lock(Rotate90::R180, Rotate90::R0, Face::South, [0.0, 8.0, 16.0, 16.0]), ([0.0, 0.0, 16.0, 8.0], Rotate90::R180));
+}
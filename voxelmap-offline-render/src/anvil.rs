@@ -0,0 +1,261 @@
+use std::io::Read;
+use std::io::Seek;
+use std::path::Path;
+use std::collections::hash_map::HashMap;
+use std::collections::hash_set::HashSet;
+use std::convert::TryFrom;
+
+use cgmath::Vector3;
+
+use mc_render::anvil::region::Region;
+use mc_render::anvil::chunk::Chunk;
+use mc_render::anvil::chunk::BiomeRef;
+use mc_render::anvil::chunk::BlockStateEntry;
+use mc_render::assets::data_type::Face;
+use mc_render::model;
+use mc_render::model::fluid::FluidTextures;
+use mc_render::model::block::World;
+use mc_render::model::block::HeightRange;
+use mc_render::model::block::BlockBounds;
+use mc_render::model::biome::BiomeColor;
+use mc_render::glrender::MeshGenerator;
+use mc_render::glrender::texture::CombinedTexture;
+use mc_render::glrender::mesh::Mesh;
+use mc_render::glrender::mesh::MeshVertex;
+
+use crate::loader::*;
+use crate::framework::TileBlock;
+
+
+const DEFAULT_BIOME: u8 = 1;        // Plains
+
+struct LoadedChunk {
+    chunk: Chunk,
+    key: Vec<usize>,    // chunk palette -> `RegionWorld::key`
+}
+
+
+/**
+ *  a 512x512 region file, blocks are addressed in region local coordinates;
+ *  `draw` renders one of the four 256x256 quarters so the output lines up with voxelmap tiles
+ */
+pub struct RegionWorld<'a> {
+    height: HeightRange,
+    chunks: Vec<Option<LoadedChunk>>,
+    key: Vec<(Vec<Model>, BlockProps)>,
+    water_models: Vec<Model>,
+    fluids: Option<FluidTextures<CombinedTexture>>,
+    air_props: BlockProps,
+    biome_color_gen: &'a BiomeColor,
+    issues: Vec<LoadError>,
+}
+
+impl<'a> RegionWorld<'a> {
+
+    /**
+     *  chunks that fail to decode are left out and recorded as issues
+     */
+    pub fn new<R: Read + Seek>(reader: R, pvd: &ModelProvider, biome_color_gen: &'a BiomeColor, placeholder: Option<[u8; 3]>) -> Result<Self, LoadError> {
+        let mut region = Region::new(reader)?;
+        let mut chunks = Vec::with_capacity(32 * 32);
+        let mut key = Vec::new();
+        let mut index: HashMap<BlockStateEntry, usize> = HashMap::new();
+        let mut issues = Vec::new();
+        let mut unknown_biomes = HashSet::new();
+        let mut height: Option<HeightRange> = None;
+        for cz in 0 .. 32 {
+            for cx in 0 .. 32 {
+                let chunk = match region.read_chunk(cx, cz).and_then(|tag| tag.as_ref().map(Chunk::from_nbt).transpose()) {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => {
+                        chunks.push(None);
+                        continue;
+                    },
+                    Err(error) => {
+                        issues.push(LoadError::BadChunk { x: cx, z: cz, error });
+                        chunks.push(None);
+                        continue;
+                    }
+                };
+                if chunk.is_pre_flattening() {
+                    issues.push(LoadError::UnsupportedChunk { x: cx, z: cz, data_version: chunk.data_version });
+                    chunks.push(None);
+                    continue;
+                }
+                for &id in chunk.biome_ids() {
+                    if u8::try_from(id).is_err() && !unknown_biomes.contains(&id) {
+                        unknown_biomes.insert(id);
+                        issues.push(LoadError::UnknownBiome(id));
+                    }
+                }
+                let range = chunk.height_range();
+                height = Some(match height {
+                    Some(h) => HeightRange::new(h.min.min(range.min), h.max.max(range.max)),
+                    None => range,
+                });
+                let mut chunk_key = Vec::with_capacity(chunk.palette().len());
+                for entry in chunk.palette() {
+                    let i = match index.get(entry) {
+                        Some(i) => *i,
+                        None => {
+                            let name = entry.name.as_str();
+                            let model = pvd.resolve(name, entry.state_iter());
+                            let mut props = BlockProps::new_from(name, entry.state_iter());
+                            match model {
+                                _ if !props.air && !pvd.contains(name) => {
+                                    issues.push(LoadError::UnknownBlockState(name.to_string()));
+                                    key.push(placeholder_key(pvd, placeholder));
+                                },
+                                Err(error) => {
+                                    issues.push(LoadError::UnmatchedState { name: name.to_string(), error });
+                                    key.push(placeholder_key(pvd, placeholder));
+                                },
+                                Ok(model) if !props.air && model.is_empty() => key.push(placeholder_key(pvd, placeholder)),
+                                Ok(model) => {
+                                    props.set_occlusion(&model);
+                                    key.push((model, props));
+                                },
+                            }
+                            index.insert(entry.clone(), key.len() - 1);
+                            key.len() - 1
+                        }
+                    };
+                    chunk_key.push(i);
+                }
+                chunks.push(Some(LoadedChunk { chunk, key: chunk_key }));
+            }
+        }
+        Ok(RegionWorld {
+            height: height.unwrap_or_default(),
+            chunks,
+            key,
+            water_models: pvd.get("minecraft:water", SplitIter::from(None)),
+            fluids: pvd.fluids().cloned(),
+            air_props: BlockProps::new(),
+            biome_color_gen,
+            issues,
+        })
+    }
+
+    /**
+     *  non-fatal problems found while loading, e.g. undecodable chunks
+     */
+    pub fn issues(&self) -> &[LoadError] {
+        self.issues.as_slice()
+    }
+
+    /**
+     *  blocks inside `bounds`, which is clipped to the region, meshed by section with all six faces;
+     *  quarter `(qx, qz)` of the region is `BlockBounds::columns(qx * 256, qz * 256, 256, 256, ..)`
+     */
+    pub fn draw(&'a self, bounds: &BlockBounds) -> GEResult<Vec<Mesh<MeshVertex>>> {
+        let mut res = Vec::new();
+        let (x0, x1) = (bounds.min.x.max(0), bounds.max.x.min(512));
+        let (z0, z1) = (bounds.min.z.max(0), bounds.max.z.min(512));
+        if x0 >= x1 || z0 >= z1 {
+            return Ok(res);
+        }
+        for cz in (z0 >> 4) ..= ((z1 - 1) >> 4) {
+            for cx in (x0 >> 4) ..= ((x1 - 1) >> 4) {
+                let c = match &self.chunks[(cx + cz * 32) as usize] {
+                    Some(c) => c,
+                    None => continue,
+                };
+                let mut r = MeshGenerator::new();
+                for sy in c.chunk.sections() {
+                    if sy * 16 + 16 <= bounds.min.y || sy * 16 >= bounds.max.y {
+                        continue;
+                    }
+                    let origin = Vector3::new(cx * 16, sy * 16, cz * 16);
+                    model::draw_section(&origin, bounds, &mut r, self, self.fluids.as_ref())?;
+                }
+                res.extend(r.unwrap())
+            }
+        }
+        Ok(res)
+    }
+
+    fn locate(&self, x: i32, z: i32) -> Option<(&LoadedChunk, usize, usize)> {
+        if x < 0 || z < 0 || x >= 512 || z >= 512 {
+            return None;
+        }
+        let c = self.chunks[(x >> 4) as usize + (z >> 4) as usize * 32].as_ref()?;
+        Some((c, (x & 15) as usize, (z & 15) as usize))
+    }
+
+    fn key_at(&self, loc: &Vector3<i32>) -> Option<usize> {
+        if !self.height.contains(loc.y) {
+            return None;
+        }
+        let (c, x, z) = self.locate(loc.x, loc.z)?;
+        let i = c.chunk.block(x, loc.y, z)?;
+        c.key.get(i).cloned()
+    }
+
+    /**
+     *  ids beyond `u8` fall back to plains, `new` records them as issues
+     */
+    fn biome(&self, chunk: &Chunk, x: usize, y: i32, z: usize) -> u8 {
+        let id = match chunk.biome(x, y, z) {
+            Some(BiomeRef::Id(id)) => u8::try_from(id).ok(),
+            Some(BiomeRef::Name(name)) => self.biome_color_gen.find(name).and_then(|b| u8::try_from(b.0).ok()),
+            None => None,
+        };
+        id.unwrap_or(DEFAULT_BIOME)
+    }
+}
+
+impl<'a> World<'a> for RegionWorld<'a> {
+    type Block = TileBlock<'a>;
+
+    fn get(&'a self, loc: &Vector3<i32>) -> Self::Block {
+        if let Some(k) = self.key_at(loc) {
+            if let Some((c, x, z)) = self.locate(loc.x, loc.z) {
+                let (model, props) = &self.key[k];
+                let biome = self.biome(&c.chunk, x, loc.y, z);
+                return TileBlock::new(
+                    model.as_slice(),
+                    self.water_models.as_slice(),
+                    props,
+                    c.chunk.light(x, loc.y + 1, z),
+                    props.biome_color.get_inner_color(self.biome_color_gen, biome, loc.y)
+                );
+            }
+        }
+        TileBlock::new(&self.water_models[0..0], self.water_models.as_slice(), &self.air_props, 0, [0, 0, 0])
+    }
+
+    fn height_range(&self) -> HeightRange {
+        self.height
+    }
+
+    fn is_air(&self, loc: &Vector3<i32>) -> bool {
+        match self.key_at(loc) {
+            Some(k) => self.key[k].1.air,
+            None => true,
+        }
+    }
+
+    fn occludes(&self, loc: &Vector3<i32>, face: &Face) -> bool {
+        match self.key_at(loc) {
+            Some(k) => self.key[k].1.occludes[face.index()],
+            None => false,
+        }
+    }
+
+}
+
+
+/**
+ *  `r.<x>.<z>.mca`
+ */
+pub fn parse_region_name<P: AsRef<Path>>(path: P) -> Option<(i32, i32)> {
+    let file_name = path.as_ref().file_name()?.to_str()?;
+    let mut s = file_name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let x = s.next()?.parse().ok()?;
+    let z = s.next()?.parse().ok()?;
+    if s.next().is_some() {
+        return None;
+    }
+    Some((x, z))
+}
@@ -1,5 +1,9 @@
+use std::io;
 use std::io::Read;
 use std::io::Seek;
+use std::io::Write;
+use std::io::BufReader;
+use std::io::BufWriter;
 use std::fs;
 use std::fs::File;
 use std::path::Path;
@@ -8,9 +12,13 @@ use std::rc::Rc;
 use std::cell::RefCell;
 
 use cgmath::Vector3;
-use cgmath::Matrix4;
 
 use glium::texture::MipmapsOption;
+use glium::texture::Texture2dArray;
+
+use log::info;
+use log::warn;
+use log::error;
 
 use mc_render::assets::data_type::Face;
 use mc_render::assets::resource::AssetsArchive;
@@ -18,41 +26,75 @@ use mc_render::assets::resource::BlockStateRawProvider;
 use mc_render::assets::resource::ModelRawProvider;
 use mc_render::assets::resource::TextureImageProvider;
 use mc_render::model;
+use mc_render::model::LazyModelProvider;
+use mc_render::model::fluid;
+use mc_render::model::fluid::Fluid;
+use mc_render::model::fluid::FluidTextures;
 use mc_render::model::block::RenderableBlock;
 use mc_render::model::block::World;
+use mc_render::model::block::HeightRange;
+use mc_render::model::block::BlockBounds;
 use mc_render::model::biome::BiomeColor;
+use mc_render::model::blockstate::MatchMode;
+use mc_render::model::cache;
+use mc_render::model::model::DisplayContext;
+use mc_render::model::model::BuildReport;
 use mc_render::glrender::MeshGenerator;
 use mc_render::glrender::mesh::Mesh;
 use mc_render::glrender::mesh::MeshVertex;
 use mc_render::glrender::context::Context;
 use mc_render::glrender::context::WindowHideContext;
 use mc_render::glrender;
+use mc_render::glrender::texture::CombinedTexture;
 use mc_render::glrender::texture::CombinedTextureGen;
+use mc_render::glrender::texture::TextureSet;
 use mc_render::glrender::OffScreenRenderer;
+use mc_render::glrender::camera::Camera;
+use mc_render::glrender::glow::Glow;
+use mc_render::glrender::antialias::Antialias;
 
 use crate::loader::*;
+use crate::anvil::RegionWorld;
+use crate::anvil::parse_region_name;
 
 
 pub struct TileWorld<'a> {
     water_models: Vec<Model>,
+    fluids: Option<FluidTextures<CombinedTexture>>,
     air_props: BlockProps,
     tile: Tile,
+    registry: &'a BlockRegistry<'a>,
     biome_color_gen: &'a BiomeColor,
+    water_depth: Option<WaterDepth>,
 }
 
 impl<'a> TileWorld<'a> {
 
-    pub fn new<R: Read + Seek>(reader: R, id: (i32, i32), pvd: &ModelProvider, biome_color_gen: &'a BiomeColor) -> Self {
-        TileWorld {
-            water_models: pvd.get("minecraft:water", SplitIter::from(None)),
+    /**
+     *  `water_depth` shades the water above a seafloor by its depth, otherwise all water looks the same
+     */
+    pub fn new<R: Read + Seek>(reader: R, id: (i32, i32), registry: &'a mut BlockRegistry, biome_color_gen: &'a BiomeColor, water_depth: Option<WaterDepth>) -> Result<Self, LoadError> {
+        let tile = Tile::load(reader, id, registry)?;
+        Ok(TileWorld {
+            water_models: registry.pvd().get("minecraft:water", SplitIter::from(None)),
+            fluids: registry.pvd().fluids().cloned(),
             air_props: BlockProps::new(),
-            tile: Tile::load(reader, id, pvd).unwrap(),
-            biome_color_gen
-        }
+            tile,
+            registry,
+            biome_color_gen,
+            water_depth,
+        })
+    }
+
+    pub fn tile(&self) -> &Tile {
+        &self.tile
     }
 
-    pub fn draw(&'a self) -> Vec<Mesh<MeshVertex>> {
-        let faces = [Face::Up];
+    /**
+     *  the stored blocks with their `faces`, the ones that can face the camera; a face is culled when
+     *  its `cullface` neighbour occludes it, and that is never air
+     */
+    pub fn draw(&'a self, faces: &[Face]) -> GEResult<Vec<Mesh<MeshVertex>>> {
         let mut res = Vec::new();
         for tz in 0 .. 8 {
             for tx in 0 .. 8 {
@@ -61,43 +103,38 @@ impl<'a> TileWorld<'a> {
                 for z in 0 + tz * 32 .. 32 + tz * 32 {
                     for x in 0 + tx * 32 .. 32 + tx * 32 {
                         let element = view.element(x, z); 
-                        let block = element.seafloor();
-                        if block.blockstate_id() != 0 {
-                            let loc = Vector3::new(x, block.height() as i32, z);
-                            model::draw(&faces, &loc, &mut r, self).unwrap();
-                        }
-                        let block = element.shading();
-                        if block.blockstate_id() != 0 {
-                            let loc = Vector3::new(x, block.height() as i32, z);
-                            model::draw(&faces, &loc, &mut r, self).unwrap();
-                        }
-                        let block = element.ceil();
-                        if block.blockstate_id() != 0 {
-                            let loc = Vector3::new(x, block.height() as i32, z);
-                            model::draw(&faces, &loc, &mut r, self).unwrap();
-                        }
-                        let block = element.vegetation();
-                        if block.blockstate_id() != 0 {
-                            let loc = Vector3::new(x, block.height() as i32, z);
-                            model::draw(&faces, &loc, &mut r, self).unwrap();
+                        for block in [element.seafloor(), element.shading(), element.ceil(), element.vegetation()].iter() {
+                            if block.blockstate_id() != 0 {
+                                let loc = Vector3::new(x, block.height(), z);
+                                model::draw(faces, &loc, &mut r, self)?;
+                                if let Some(fluids) = &self.fluids {
+                                    fluid::draw(faces, &loc, &mut r, self, fluids)?;
+                                }
+                            }
                         }
                     }
                 }
                 res.extend(r.unwrap())
             }
         }
-        res
+        Ok(res)
     }
 
     fn gen(&'a self, block: LayerView<'a>, element: ElementView<'a>) -> TileBlock<'a> {
         let id = block.blockstate_id();
-        let (model, props) = self.tile.get_model(id);
+        let (model, props) = self.registry.get(self.tile.global_id(id));
+        let [r, g, b] = props.biome_color.get_inner_color(self.biome_color_gen, element.biome(), block.height());
+        let seafloor = element.seafloor();
+        let color = match self.water_depth {
+            Some(depth) if props.water && seafloor.blockstate_id() != 0 => depth.shade([r, g, b], block.height() - seafloor.height()),
+            _ => [r, g, b, 255],
+        };
         TileBlock {
             model: model.as_slice(),
             water: self.water_models.as_slice(),
             props,
             light: block.light(),
-            color: props.biome_color.get_inner_color(self.biome_color_gen, element.biome(), block.height()),
+            color,
         }
     }
 
@@ -107,7 +144,7 @@ impl<'a> TileWorld<'a> {
             water: &self.water_models[..],
             props: &self.air_props,
             light: 0,
-            color: [0, 0, 0]
+            color: [0, 0, 0, 255]
         }
     }
 }
@@ -115,46 +152,53 @@ impl<'a> TileWorld<'a> {
 impl<'a> World<'a> for TileWorld<'a> {
     type Block = TileBlock<'a>;
 
+    /**
+     *  layers with blockstate id 0 are empty, whatever height they are stored at
+     */
     fn get(&'a self, loc: &Vector3<i32>) -> Self::Block {
-        if !(loc.x < 0 || loc.y < 0 || loc.z < 0 || loc.x > 255 || loc.y > 255 || loc.z > 255) {
+        if !(loc.x < 0 || loc.z < 0 || loc.x > 255 || loc.z > 255) && self.height_range().contains(loc.y) {
             let element = self.tile.view().element(loc.x, loc.z);
             let block = element.ceil();
-            if block.height() as i32 == loc.y {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return self.gen(block, element);
             }
             let block = element.seafloor();
-            if block.height() as i32 == loc.y {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return self.gen(block, element);
             }
             let block = element.shading();
-            if block.height() as i32 == loc.y {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return self.gen(block, element);
             }
             let block = element.vegetation();
-            if block.height() as i32 == loc.y {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return self.gen(block, element);
             }
         }
         self.air()
     }
+
+    fn height_range(&self) -> HeightRange {
+        self.tile.format().height_range()
+    }
     
     fn is_air(&self, loc: &Vector3<i32>) -> bool {
-        if !(loc.x < 0 || loc.y < 0 || loc.z < 0 || loc.x > 255 || loc.y > 255 || loc.z > 255) {
+        if !(loc.x < 0 || loc.z < 0 || loc.x > 255 || loc.z > 255) && self.height_range().contains(loc.y) {
             let element = self.tile.view().element(loc.x, loc.z);
             let block = element.ceil();
-            if block.height() as i32 == loc.y && block.blockstate_id() != 0 {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return false;
             }
             let block = element.seafloor();
-            if block.height() as i32 == loc.y && block.blockstate_id() != 0 {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return false;
             }
             let block = element.shading();
-            if block.height() as i32 == loc.y && block.blockstate_id() != 0 {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return false;
             }
             let block = element.vegetation();
-            if block.height() as i32 == loc.y && block.blockstate_id() != 0 {
+            if block.height() == loc.y && block.blockstate_id() != 0 {
                 return false;
             }
         }
@@ -169,7 +213,20 @@ pub struct TileBlock<'a> {
     water: &'a [Model],
     props: &'a BlockProps,
     light: u8,
-    color: [u8; 3]
+    color: [u8; 4]
+}
+
+impl<'a> TileBlock<'a> {
+
+    pub fn new(model: &'a [Model], water: &'a [Model], props: &'a BlockProps, light: u8, color: [u8; 3]) -> Self {
+        TileBlock {
+            model,
+            water,
+            props,
+            light,
+            color: [color[0], color[1], color[2], 255],
+        }
+    }
 }
 
 
@@ -198,12 +255,44 @@ impl<'a> RenderableBlock<'a> for TileBlock<'a> {
 
     fn get_inline_color(&self, tintindex: usize) -> [u8; 4] {
         let _ = tintindex;
-        [self.color[0], self.color[1], self.color[2], 255]
+        self.color
     }
 
     fn get_light(&self) -> u8 {
         self.light
     }
+
+    fn get_emission(&self) -> u8 {
+        self.props.emission
+    }
+
+    fn get_fluid_level(&self) -> Option<u8> {
+        self.props.fluid_level
+    }
+
+    fn is_snowy(&self) -> bool {
+        self.props.snowy
+    }
+
+    fn get_fluid(&self) -> Option<Fluid> {
+        self.props.fluid
+    }
+
+    fn occludes(&self, face: &Face) -> bool {
+        self.props.occludes[face.index()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Voxelmap,   // `<x>,<z>.zip` voxelmap cache
+    Anvil,      // `r.<x>.<z>.mca` region files of a world save
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum View {
+    TopDown,
+    Isometric,
 }
 
 pub struct AppOptions {
@@ -213,10 +302,19 @@ pub struct AppOptions {
     pub tex_height: u32,
     pub assets: Vec<String>,
     pub cache_folder: String,
+    pub input_format: InputFormat,
+    pub view: View,
     pub output_folder: String,
-    pub world: Matrix4<f32>,
-    pub center: Vector3<i32>,
+    pub report_file: Option<String>,
+    pub blocks_report: Option<String>,
+    pub strict: bool,
+    pub model_cache: Option<String>,
+    pub lazy: bool,
+    pub placeholder_color: Option<[u8; 3]>,
+    pub water_depth: Option<WaterDepth>,
     pub night_mod: bool,
+    pub glow: Option<Glow>,
+    pub antialias: Antialias,
 }
 
 impl Default for AppOptions {
@@ -229,14 +327,37 @@ impl Default for AppOptions {
             tex_height: 16,
             assets: Vec::new(),
             cache_folder: String::from("."),
+            input_format: InputFormat::Voxelmap,
+            view: View::TopDown,
             output_folder: String::from("../image"),
-            world: Matrix4::from_angle_x(cgmath::Deg(90.0)) * Matrix4::from_scale(1.0 / 128.0),
-            center: Vector3::new(128, 0, 128),
+            report_file: None,
+            blocks_report: None,
+            strict: false,
+            model_cache: None,
+            lazy: false,
+            placeholder_color: Some([0xF8, 0x00, 0xF8]),
+            water_depth: None,
             night_mod: false,
+            glow: None,
+            antialias: Antialias::default(),
         }
     }
 }
 
+/**
+ *  camera framing `bounds` in the requested `view`
+ */
+pub fn camera(view: View, bounds: BlockBounds, aspect: f32) -> Camera {
+    match view {
+        View::TopDown => Camera::top_down(bounds, aspect),
+        View::Isometric => Camera::isometric(bounds, aspect),
+    }
+}
+
+const ICON_BIOME: u8 = 1;      // Plains
+
+const ICON_HEIGHT: i32 = 64;
+
 pub fn wrap_assets(assets: Vec<String>) -> Vec<File> {
     let mut res = Vec::new();
     for s in assets {
@@ -245,7 +366,7 @@ pub fn wrap_assets(assets: Vec<String>) -> Vec<File> {
                 res.push(ifile);
             },
             Err(e) => {
-                eprintln!("file [{}]: {}", s.as_str(), e);
+                error!("file [{}]: {}", s.as_str(), e);
             }
         }
     }
@@ -254,44 +375,290 @@ pub fn wrap_assets(assets: Vec<String>) -> Vec<File> {
 
 
 
-pub fn app(options: AppOptions) -> GEResult<()> {
-
-    if let Err(e) = fs::create_dir_all(options.output_folder.as_str()) {
+fn create_output_folder(folder: &str) -> GEResult<()> {
+    if let Err(e) = fs::create_dir_all(folder) {
         if e.kind() != std::io::ErrorKind::AlreadyExists {
             return Err(Box::new(e));
         }
     }
+    Ok(())
+}
 
-    let assets = wrap_assets(options.assets);
-    let mut ctx = WindowHideContext::build(options.width, options.height, glium::glutin::GlRequest::Specific(glium::glutin::Api::OpenGl, (3, 3)));
-    let assets = Rc::new(RefCell::new(AssetsArchive::from_list(assets.into_iter()).map_err(Box::new)?));
+/**
+ *  every blockstate of the asset stack, with textures packed on `ctx`
+ */
+fn build_models(ctx: &WindowHideContext, options: &AppOptions) -> GEResult<(ModelProvider, Texture2dArray)> {
+    let lazy = match (options.lazy, options.input_format) {
+        (true, InputFormat::Voxelmap) => true,
+        (true, InputFormat::Anvil) => {
+            info!("lazy build: anvil input, building every blockstate");
+            false
+        },
+        (false, _) => false,
+    };
+    let cached = match &options.model_cache {
+        Some(_) if lazy => {
+            warn!("model cache: not used by a lazy build");
+            None
+        },
+        Some(path) => load_models(path, options),
+        None => None,
+    };
+    let (mut modelpvd, set) = match cached {
+        Some(v) => v,
+        None if lazy => build_models_lazy(ctx, options)?,
+        None => {
+            let built = build_models_from_assets(ctx, options)?;
+            if let Some(path) = &options.model_cache {
+                save_models(path, options, &built);
+            }
+            built
+        }
+    };
+    if let Some(blocks_report) = &options.blocks_report {
+        let n = modelpvd.load_properties(File::open(blocks_report).map_err(Box::new)?).map_err(Box::new)?;
+        info!("block properties: {} block(s) from {}", n, blocks_report);
+    }
+    modelpvd.set_mode(if options.strict { MatchMode::Strict } else { MatchMode::Lenient });
+    let textures = set.build(ctx.facade(), options.tex_width, options.tex_height, MipmapsOption::NoMipmap)?;
+    Ok((modelpvd, textures))
+}
+
+fn open_assets(options: &AppOptions) -> GEResult<Rc<RefCell<AssetsArchive<File>>>> {
+    let assets = wrap_assets(options.assets.clone());
+    Ok(Rc::new(RefCell::new(AssetsArchive::from_list(assets).map_err(Box::new)?)))
+}
+
+fn write_build_report(options: &AppOptions, report: &BuildReport) -> GEResult<()> {
+    info!(
+        "model build: {} failed blockstate(s), {} unresolved texture reference(s), {} empty model(s)", 
+        report.failed.len(), report.unresolved_textures.len(), report.empty_models.len()
+    );
+    if let Some(report_file) = &options.report_file {
+        fs::write(report_file, report.to_json().map_err(Box::new)?).map_err(Box::new)?;
+    }
+    Ok(())
+}
+
+fn build_models_from_assets(ctx: &WindowHideContext, options: &AppOptions) -> GEResult<(ModelProvider, TextureSet)> {
+    let assets = open_assets(options)?;
     let list = assets.borrow_mut().find_blockstates();
     let mut bs_pvd = BlockStateRawProvider::from(assets.clone());
     let mut mdl_pvd = ModelRawProvider::from(assets.clone());
     let tex_pvd = TextureImageProvider::from(assets.clone());
     let mut tex_gen = CombinedTextureGen::new(ctx.facade(), tex_pvd);
     let mut modelpvd = ModelProvider::new();
-    modelpvd.build("minecraft", list.into_iter(), &mut bs_pvd, &mut mdl_pvd, &mut tex_gen);
-    let textures = tex_gen.build(options.tex_width, options.tex_height, MipmapsOption::NoMipmap)?;
+    let report = modelpvd.build("minecraft", list.into_iter(), &mut bs_pvd, &mut mdl_pvd, &mut tex_gen);
+    write_build_report(options, &report)?;
+    Ok((modelpvd, tex_gen.into_set()))
+}
+
+/**
+ *  only the blockstates the `key` of some tile in `options.cache_folder` names, so every texture 
+ *  is known before the texture array is built
+ */
+fn build_models_lazy(ctx: &WindowHideContext, options: &AppOptions) -> GEResult<(ModelProvider, TextureSet)> {
+    let assets = open_assets(options)?;
+    let bs_pvd = BlockStateRawProvider::from(assets.clone());
+    let mdl_pvd = ModelRawProvider::from(assets.clone());
+    let tex_pvd = TextureImageProvider::from(assets.clone());
+    let lazy = LazyModelProvider::new("minecraft", bs_pvd, mdl_pvd, CombinedTextureGen::new(ctx.facade(), tex_pvd));
+    lazy.load("minecraft:water");
+    for path in fs::read_dir(options.cache_folder.as_str()).map_err(Box::new)? {
+        let path = path.map_err(Box::new)?.path();
+        if parse_file_name(&path).is_none() {
+            continue;
+        }
+        match File::open(&path).map_err(LoadError::from).and_then(Tile::scan_names) {
+            Ok(names) => for name in names {
+                lazy.load(&name);
+            },
+            Err(e) => warn!("{}: {}", path.display(), e),
+        }
+    }
+    info!("lazy build: {} blockstate(s) referenced", lazy.len());
+    let (modelpvd, tex_gen, report) = lazy.into_inner();
+    write_build_report(options, &report)?;
+    Ok((modelpvd, tex_gen.into_set()))
+}
+
+/**
+ *  the models cached in `path`, if they were built from the same assets
+ */
+fn load_models(path: &str, options: &AppOptions) -> Option<(ModelProvider, TextureSet)> {
+    let result = cache::fingerprint(&options.assets).and_then(|fingerprint| {
+        let mut reader = BufReader::new(File::open(path)?);
+        cache::load(&mut reader, fingerprint)
+    });
+    match result {
+        Ok(Some((modelpvd, set))) => {
+            info!("model cache: loaded from {}", path);
+            if options.report_file.is_some() {
+                warn!("model cache: no build report for cached models");
+            }
+            Some((modelpvd, set))
+        },
+        Ok(None) => {
+            info!("model cache: {} is stale, rebuilding", path);
+            None
+        },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("model cache [{}]: {}", path, e);
+            None
+        }
+    }
+}
+
+fn save_models(path: &str, options: &AppOptions, models: &(ModelProvider, TextureSet)) {
+    let result = cache::fingerprint(&options.assets).and_then(|fingerprint| {
+        let mut writer = BufWriter::new(File::create(path)?);
+        cache::save(&mut writer, fingerprint, models)?;
+        writer.flush()
+    });
+    match result {
+        Ok(()) => info!("model cache: saved to {}", path),
+        Err(e) => warn!("model cache [{}]: {}", path, e),
+    }
+}
+
+pub fn app(options: AppOptions) -> GEResult<()> {
+
+    create_output_folder(options.output_folder.as_str())?;
+
+    let mut ctx = WindowHideContext::build(options.width, options.height, glium::glutin::GlRequest::Specific(glium::glutin::Api::OpenGl, (3, 3)));
+    let (modelpvd, textures) = build_models(&ctx, &options)?;
     let light_map = glium::texture::Texture2d::new(ctx.facade(), glrender::default_lmmp(options.night_mod)).unwrap();
     let mut renderer = OffScreenRenderer::new(&ctx, &textures, &light_map);
+    renderer.set_glow(options.glow);
+    renderer.set_antialias(options.antialias);
     let biome_color_gen = BiomeColor::new();
 
+    let output_folder = options.output_folder.as_str();
+    let placeholder_color = options.placeholder_color;
+    let water_depth = options.water_depth;
+    let view = options.view;
+    let aspect = options.width as f32 / options.height as f32;
+    let save = |img: image::RgbaImage, id: (i32, i32)| -> GEResult<()> {
+        let mut path = PathBuf::from(output_folder);
+        path.push(format!("{},{}.png", id.0, id.1));
+        img.save_with_format(&path, image::ImageFormat::PNG).map_err(Box::new)?;
+        Ok(())
+    };
+    let mut registry = BlockRegistry::new(&modelpvd, placeholder_color);
+    let mut render_tile = |renderer: &mut OffScreenRenderer<_>, path: &Path, id: (i32, i32)| -> GEResult<usize> {
+        let world = TileWorld::new(File::open(path).map_err(LoadError::from)?, id, &mut registry, &biome_color_gen, water_depth)?;
+        for issue in world.tile().issues() {
+            warn!("{}: {}", path.display(), issue);
+        }
+        let camera = camera(view, BlockBounds::columns(0, 0, 256, 256, &world.height_range()), aspect);
+        let mesh = world.draw(&camera.faces())?;
+        save(renderer.draw_camera(mesh.iter(), &camera)?, id)?;
+        Ok(world.tile().issues().len())
+    };
+    let render_anvil = |renderer: &mut OffScreenRenderer<_>, path: &Path, id: (i32, i32)| -> GEResult<usize> {
+        let world = RegionWorld::new(File::open(path).map_err(LoadError::from)?, &modelpvd, &biome_color_gen, placeholder_color)?;
+        for issue in world.issues() {
+            warn!("{}: {}", path.display(), issue);
+        }
+        for qz in 0 .. 2 {
+            for qx in 0 .. 2 {
+                let camera = camera(view, BlockBounds::columns(qx * 256, qz * 256, 256, 256, &world.height_range()), aspect);
+                let mesh = world.draw(&camera.bounds())?;
+                save(renderer.draw_camera(mesh.iter(), &camera)?, (id.0 * 2 + qx, id.1 * 2 + qz))?;
+            }
+        }
+        Ok(world.issues().len())
+    };
+    let mut rendered = 0;
+    let mut warned = 0;
+    let mut failed = Vec::new();
     for path in fs::read_dir(options.cache_folder.as_str()).map_err(Box::new)? {
         let path = path.map_err(Box::new)?.path();
-        if let Some(id) = parse_file_name(&path) {
-            println!("path {}", path.display());
-            let world = TileWorld::new(File::open(&path).map_err(Box::new)?, id, &modelpvd, &biome_color_gen);
-            let mesh = world.draw();
-            let img = renderer.draw(mesh.iter(), options.world, options.center)?;
-            let mut path = PathBuf::from(options.output_folder.as_str());
-            path.push(format!("{},{}.png", id.0, id.1));
-            if let Err(e) = img.save_with_format(&path, image::ImageFormat::PNG) {
-                eprintln!("{}", e);
+        let id = match options.input_format {
+            InputFormat::Voxelmap => parse_file_name(&path),
+            InputFormat::Anvil => parse_region_name(&path),
+        };
+        if let Some(id) = id {
+            info!("path {}", path.display());
+            let result = match options.input_format {
+                InputFormat::Voxelmap => render_tile(&mut renderer, &path, id),
+                InputFormat::Anvil => render_anvil(&mut renderer, &path, id),
+            };
+            match result {
+                Ok(issues) => {
+                    rendered += 1;
+                    if issues > 0 {
+                        warned += 1;
+                    }
+                },
+                Err(e) => {
+                    error!("{}: {}", path.display(), e);
+                    failed.push((path, e));
+                }
             }
         }      
     }
 
+    if let InputFormat::Voxelmap = options.input_format {
+        info!("{} distinct blockstate(s) in the keys", registry.len());
+    }
+    info!("rendered {} region(s), {} with key issues, {} failed", rendered, warned, failed.len());
+    for (path, e) in &failed {
+        info!("  {}: {}", path.display(), e);
+    }
+
+    ctx.wait();
+
+    Ok(())
+}
+
+
+/**
+ *  one `options.width` square icon per blockstate, `<namespace>/<name>.png` in the output folder
+ */
+pub fn icons(options: AppOptions, context: DisplayContext) -> GEResult<()> {
+
+    create_output_folder(options.output_folder.as_str())?;
+
+    let mut ctx = WindowHideContext::build(options.width, options.width, glium::glutin::GlRequest::Specific(glium::glutin::Api::OpenGl, (3, 3)));
+    let (modelpvd, textures) = build_models(&ctx, &options)?;
+    let light_map = glium::texture::Texture2d::new(ctx.facade(), glrender::default_lmmp(false)).unwrap();
+    let mut renderer = OffScreenRenderer::new(&ctx, &textures, &light_map);
+    renderer.set_antialias(options.antialias);
+    let biome_color_gen = BiomeColor::new();
+
+    let mut names: Vec<&str> = modelpvd.names().collect();
+    names.sort_unstable();
+    let mut failed = 0;
+    for name in names {
+        let models = modelpvd.get_any(name);
+        if models.is_empty() {
+            continue;
+        }
+        let props = BlockProps::new_from(name, SplitIter::from(None));
+        let [r, g, b] = props.biome_color.get_inner_color(&biome_color_gen, ICON_BIOME, ICON_HEIGHT);
+        let (namespace, block) = match name.find(':') {
+            Some(i) => (&name[..i], &name[i + 1..]),
+            None => ("minecraft", name),
+        };
+        let mut path = PathBuf::from(options.output_folder.as_str());
+        path.push(namespace);
+        if let Err(e) = fs::create_dir_all(&path) {
+            error!("{}: {}", path.display(), e);
+            failed += 1;
+            continue;
+        }
+        path.push(format!("{}.png", block));
+        let result = renderer.draw_display(&models, context, [r, g, b, 255])
+            .and_then(|img| img.save_with_format(&path, image::ImageFormat::PNG).map_err(|e| e.into()));
+        if let Err(e) = result {
+            error!("{}: {}", name, e);
+            failed += 1;
+        }
+    }
+    info!("{} icon(s) failed", failed);
+
     ctx.wait();
 
     Ok(())
@@ -309,4 +676,95 @@ pub fn parse_file_name<P: AsRef<Path>>(path: P) -> Option<(i32, i32)> {
     } else {
         None
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use mc_render::assets::util::Provider;
+    use mc_render::assets::data_raw::BlockStateRaw;
+    use mc_render::assets::data_raw::ModelRaw;
+    use mc_render::model::model::TextureGen;
+
+    use super::*;
+
+    struct NoAssets;
+
+    impl Provider for NoAssets {
+        type Item = BlockStateRaw;
+
+        fn provide(&mut self, _name: &str) -> Option<Self::Item> {
+            None
+        }
+    }
+
+    struct NoModels;
+
+    impl Provider for NoModels {
+        type Item = ModelRaw;
+
+        fn provide(&mut self, _name: &str) -> Option<Self::Item> {
+            None
+        }
+    }
+
+    struct FirstTexture;
+
+    impl TextureGen for FirstTexture {
+        type Texture = CombinedTexture;
+
+        fn get(&mut self, _name: &str) -> Self::Texture {
+            CombinedTexture(1)
+        }
+    }
+
+    /**
+     *  a tile of empty layers at height 0 but the top layer of the element at `x`, 0, which is
+     *  key id `id` at height `y`
+     */
+    fn tile(key: &str, x: i32, y: u8, id: u8) -> Vec<u8> {
+        let format = TileFormat::Legacy;
+        let mut data = vec![0u8; format.data_size()];
+        let i = x as usize * format.element_size() + 2 * format.layer_size();
+        data[i] = y;
+        data[i + 2] = id;
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("data", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(&data).unwrap();
+        zip.start_file("key", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(key.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_empty_column_next_to_block() {
+        let mut pvd = ModelProvider::new();
+        pvd.build("minecraft", std::iter::once("water".to_string()), &mut NoAssets, &mut NoModels, &mut FirstTexture);
+        let mut registry = BlockRegistry::new(&pvd, Some([255, 0, 255]));
+        let biome_color_gen = BiomeColor::new();
+        let data = tile("1 Block{minecraft:water}", 1, 0, 1);
+        let world = TileWorld::new(Cursor::new(data), (0, 0), &mut registry, &biome_color_gen, None).unwrap();
+        // the columns around are stored as id 0 at height 0, level with the water
+        assert!(world.get(&Vector3::new(0, 0, 0)).is_air());
+        assert!(!world.occludes(&Vector3::new(2, 0, 0), &Face::West));
+        assert!(!world.get(&Vector3::new(1, 0, 0)).is_air());
+        let meshes = world.draw(&Face::ALL).unwrap();
+        assert!(!meshes.is_empty());
+    }
+
+    #[test]
+    fn test_id_past_the_key() {
+        let mut pvd = ModelProvider::new();
+        pvd.build("minecraft", std::iter::once("water".to_string()), &mut NoAssets, &mut NoModels, &mut FirstTexture);
+        let mut registry = BlockRegistry::new(&pvd, Some([255, 0, 255]));
+        let biome_color_gen = BiomeColor::new();
+        let data = tile("1 Block{minecraft:water}", 1, 64, 2);
+        let world = TileWorld::new(Cursor::new(data), (0, 0), &mut registry, &biome_color_gen, None).unwrap();
+        assert!(matches!(world.tile().issues(), [LoadError::MissingKeyId(2)]));
+        // drawn as the placeholder
+        assert_eq!(world.tile().global_id(2), 0);
+        assert!(!world.get(&Vector3::new(1, 64, 0)).is_air());
+        assert!(!world.draw(&Face::ALL).unwrap().is_empty());
+    }
+}
@@ -1,79 +1,228 @@
+use std::io;
+use std::collections::hash_map::HashMap;
 use std::io::Read;
 use std::io::Seek;
 use std::str::Split;
+use std::fmt;
 use std::convert::TryFrom;
 
 use zip::ZipArchive;
+use zip::result::ZipError;
 
+use mc_render::assets::data_type::Face;
 use mc_render::model;
 use mc_render::model::biome::BiomeColor;
 use mc_render::model::biome::Biome;
-use mc_render::model::model::RefModel;
+use mc_render::model::block::HeightRange;
+use mc_render::model::model::WeightedModel;
+use mc_render::model::blockstate::Weighted;
+use mc_render::model::blockstate::MatchError;
+use mc_render::model::blockstate::BlockStateKey;
+use mc_render::model::fluid::Fluid;
 use mc_render::glrender::texture::CombinedTexture;
 
 pub type GEResult<T> = Result<T, Box<dyn std::error::Error>>;
-pub type Model = RefModel<CombinedTexture>;
+pub type Model = WeightedModel<CombinedTexture>;
 pub type ModelProvider = model::ModelProvider<CombinedTexture>;
 
+
+/**
+ *  errors of a single region; `KeyLine`, `UnknownBlock`, `MissingKeyId`, `BadChunk`, `UnsupportedChunk`,
+ *  `UnknownBiome`, `UnknownBlockState` and `UnmatchedState` are only recorded as issues
+ */
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    BadZip(ZipError),
+    MissingEntry(&'static str),
+    SizeMismatch(usize),
+    KeyLine { line: usize, column: usize },
+    UnknownBlock { line: usize, name: String },
+    MissingKeyId(u16),
+    BadChunk { x: usize, z: usize, error: io::Error },
+    UnsupportedChunk { x: usize, z: usize, data_version: i32 },
+    UnknownBiome(i32),
+    UnknownBlockState(String),
+    UnmatchedState { name: String, error: MatchError },
+}
+
+impl LoadError {
+
+    fn at_line(self, line: usize) -> Self {
+        match self {
+            Self::KeyLine { column, .. } => Self::KeyLine { line, column },
+            Self::UnknownBlock { name, .. } => Self::UnknownBlock { line, name },
+            e => e,
+        }
+    }
+
+    fn entry(name: &'static str) -> impl FnOnce(ZipError) -> Self {
+        move |e| match e {
+            ZipError::FileNotFound => Self::MissingEntry(name),
+            e => Self::BadZip(e),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {}", e),
+            Self::BadZip(e) => write!(f, "bad zip: {}", e),
+            Self::MissingEntry(name) => write!(f, "missing entry `{}`", name),
+            Self::SizeMismatch(n) => write!(f, "unexpected `data` size {}", n),
+            Self::KeyLine { line, column } => write!(f, "malformed key line {} @{}", line, column),
+            Self::UnknownBlock { line, name } => write!(f, "unknown block `{}` at key line {}", name, line),
+            Self::MissingKeyId(id) => write!(f, "block id {} is not in the key, drawn as the placeholder", id),
+            Self::BadChunk { x, z, error } => write!(f, "bad chunk ({}, {}): {}", x, z, error),
+            Self::UnsupportedChunk { x, z, data_version } => write!(f, "chunk ({}, {}): numeric block ids of data version {} (before 1.13) are not supported", x, z, data_version),
+            Self::UnknownBiome(id) => write!(f, "biome id {} out of range, drawn as plains", id),
+            Self::UnknownBlockState(name) => write!(f, "unknown block `{}`", name),
+            Self::UnmatchedState { name, error } => write!(f, "block `{}`: {}", name, error),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::BadZip(e) => Some(e),
+            Self::BadChunk { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl From<ZipError> for LoadError {
+
+    fn from(e: ZipError) -> Self {
+        LoadError::BadZip(e)
+    }
+}
+
+/**
+ *  layout of the `data` entry.
+ *  `Legacy`: 18 bytes per element, each layer is [height: u8, blockstate: u16, light: u8],
+ *  followed by [_, biome]; the only layout known, its heights cover 0 to 255
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileFormat {
+    Legacy,
+}
+
+impl TileFormat {
+
+    pub fn detect(data_len: usize) -> Option<Self> {
+        if data_len == TileFormat::Legacy.data_size() {
+            Some(TileFormat::Legacy)
+        } else {
+            None
+        }
+    }
+
+    pub fn layer_size(&self) -> usize {
+        match self {
+            Self::Legacy => 4,
+        }
+    }
+
+    pub fn element_size(&self) -> usize {
+        self.layer_size() * 4 + 2
+    }
+
+    pub fn data_size(&self) -> usize {
+        256 * 256 * self.element_size()
+    }
+
+    pub fn height_range(&self) -> HeightRange {
+        match self {
+            Self::Legacy => HeightRange::LEGACY,
+        }
+    }
+}
+
+
 pub struct LayerView<'a> {
     raw: &'a[u8],
+    format: TileFormat,
 }
 
 impl<'a> LayerView<'a> {
 
-    pub fn height(&self) -> u8 {
-        self.raw[0]
+    pub fn height(&self) -> i32 {
+        match self.format {
+            TileFormat::Legacy => self.raw[0] as i32,
+        }
     }
 
     pub fn blockstate_id(&self) -> u16 {
-        ((self.raw[1] as u16) << 8) | (self.raw[2] as u16) 
+        let i = self.format.layer_size() - 3;
+        ((self.raw[i] as u16) << 8) | (self.raw[i + 1] as u16) 
     }
 
     pub fn light(&self) -> u8 {
-        self.raw[3]
+        self.raw[self.format.layer_size() - 1]
     }
 }
 
 pub struct ElementView<'a> {
     raw: &'a[u8],
+    format: TileFormat,
 }
 
 impl<'a> ElementView<'a> {
 
+    fn layer(&self, i: usize) -> LayerView<'a> {
+        let n = self.format.layer_size();
+        LayerView { raw: &self.raw[i * n .. (i + 1) * n], format: self.format }
+    }
+
     pub fn shading(&self) -> LayerView<'a> {
-        LayerView { raw: &self.raw[0..4] }
+        self.layer(0)
     }
 
     pub fn seafloor(&self) -> LayerView<'a> {
-        LayerView { raw: &self.raw[4..8] }
+        self.layer(1)
     }
 
     pub fn ceil(&self) -> LayerView<'a> {
-        LayerView { raw: &self.raw[8..12] }
+        self.layer(2)
     }
 
     pub fn vegetation(&self) -> LayerView<'a> {
-        LayerView { raw: &self.raw[12..16] }
+        self.layer(3)
     }
 
     // pub fn _(&self) -> u8 {
-    //     self.raw[16]
+    //     self.raw[self.raw.len() - 2]
     // }
 
     pub fn biome(&self) -> u8 {
-        self.raw[17]
+        self.raw[self.raw.len() - 1]
     }
 }
 
 pub struct TileView<'a> {
     raw: &'a[u8],
+    format: TileFormat,
 }
 
 impl<'a> TileView<'a> {
 
     pub fn element(&self, x: i32, z: i32) -> ElementView<'a> {
-        let index = ((x + z * 256) * 18) as usize;
-        ElementView { raw: &self.raw[index .. index + 18] }
+        let n = self.format.element_size();
+        let index = (x + z * 256) as usize * n;
+        ElementView { raw: &self.raw[index .. index + n], format: self.format }
     }
 }
 
@@ -84,6 +233,7 @@ pub enum InnerColor {
     Water,
     Grass,
     Foliage,
+    Fixed([u8; 3]),
 }
 
 impl From<&str> for InnerColor {
@@ -105,16 +255,60 @@ impl From<&str> for InnerColor {
 
 impl InnerColor {
 
-    pub fn get_inner_color(&self, biome_color_gen: &BiomeColor, biome: u8, height: u8) -> [u8; 3] {
+    pub fn get_inner_color(&self, biome_color_gen: &BiomeColor, biome: u8, height: i32) -> [u8; 3] {
         match self {
             Self::None => [255, 255, 255],
             Self::Water => biome_color_gen.get_water(&Biome(biome as usize)),
-            Self::Grass => biome_color_gen.get_grass(&Biome(biome as usize), height as i32),
-            Self::Foliage => biome_color_gen.get_foliage(&Biome(biome as usize), height as i32),
+            Self::Grass => biome_color_gen.get_grass(&Biome(biome as usize), height),
+            Self::Foliage => biome_color_gen.get_foliage(&Biome(biome as usize), height),
+            Self::Fixed(color) => *color,
+        }
+    }
+}
+
+
+/**
+ *  water darkened and made opaque with the depth down to the seafloor, like the oceans of in-game maps
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WaterDepth {
+
+    pub max_depth: i32,     // deeper water is drawn as deep as this, above zero
+
+    pub curve: f32,         // exponent of the depth fraction, above zero; below 1 darkens shallow water faster
+
+}
+
+impl Default for WaterDepth {
+
+    fn default() -> Self {
+        WaterDepth {
+            max_depth: 32,
+            curve: 0.5,
         }
     }
 }
 
+impl WaterDepth {
+
+    const SHALLOW_ALPHA: f32 = 0.45;
+
+    const DEEP_SHADE: f32 = 0.35;
+
+    /**
+     *  `color` of water `depth` blocks above the seafloor, with the alpha the water quad is drawn with
+     */
+    pub fn shade(&self, color: [u8; 3], depth: i32) -> [u8; 4] {
+        debug_assert!(self.max_depth > 0 && self.curve > 0.0, "{:?}", self);
+        let t = (depth.max(0) as f32 / self.max_depth as f32).min(1.0).powf(self.curve);
+        let shade = 1.0 - (1.0 - Self::DEEP_SHADE) * t;
+        let alpha = Self::SHALLOW_ALPHA + (1.0 - Self::SHALLOW_ALPHA) * t;
+        let [r, g, b] = color;
+        let f = |c: u8| (f32::from(c) * shade).round() as u8;
+        [f(r), f(g), f(b), (alpha * 255.0).round() as u8]
+    }
+}
+
 
 pub struct BlockProps {
 
@@ -124,7 +318,17 @@ pub struct BlockProps {
 
     pub waterlogged: bool,
 
+    pub occludes: [bool; 6],    // by `Face::index`, see `RenderableBlock::occludes`
+
     pub biome_color: InnerColor,
+
+    pub emission: u8,   // block light the block gives off, 0 to 15
+
+    pub fluid: Option<Fluid>,
+
+    pub fluid_level: Option<u8>,    // of water, lava and waterlogged blocks; 0 is a source
+
+    pub snowy: bool,
 }
 
 impl BlockProps {
@@ -134,78 +338,291 @@ impl BlockProps {
             air: true,
             water: false,
             waterlogged: false,
+            occludes: [false; 6],
             biome_color: InnerColor::None,
+            emission: 0,
+            fluid: None,
+            fluid_level: None,
+            snowy: false,
         }
     }
 
     pub fn new_from<'a, I: Iterator<Item = &'a str>>(name: &'a str, state: I) -> Self {
-        let mut waterlogged = false;
-        for s in state {
-            let mut it = s.split('=');
-            if it.next() == Some("waterlogged") {
-                if it.next() == Some("true") {
-                    waterlogged = true;
-                }
-            }
-        }
+        Self::from_state(&BlockStateKey::new(name, state))
+    }
+
+    pub fn from_state(state: &BlockStateKey) -> Self {
+        let name = state.name.as_str();
+        let waterlogged = state.waterlogged();
+        let (fluid, fluid_level) = match name {
+            "minecraft:water" => (Some(Fluid::Water), Some(state.level().unwrap_or(0))),
+            "minecraft:lava" => (Some(Fluid::Lava), Some(state.level().unwrap_or(0))),
+            "minecraft:bubble_column" => (Some(Fluid::Water), Some(0)),
+            _ if waterlogged => (Some(Fluid::Water), Some(0)),
+            _ => (None, None),
+        };
         BlockProps {
-            air: name == "minecraft:air",
+            air: matches!(name, "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"),
             water: name == "minecraft:water",
             waterlogged,
-            biome_color: InnerColor::from(name)
+            occludes: [false; 6],
+            biome_color: InnerColor::from(name),
+            emission: emission(state),
+            fluid,
+            fluid_level,
+            snowy: state.snowy(),
+        }
+    }
+
+    /**
+     *  a face occludes if any of the models covers it, whichever alternative is picked
+     */
+    pub fn set_occlusion(&mut self, models: &[Model]) {
+        for face in Face::ALL.iter() {
+            self.occludes[face.index()] = !self.air && models.iter().any(|m| !m.is_empty() && m.iter().all(|t| t.occludes(face.clone())));
+        }
+    }
+
+    pub fn placeholder(color: [u8; 3]) -> Self {
+        BlockProps {
+            air: false,
+            water: false,
+            waterlogged: false,
+            occludes: [true; 6],
+            biome_color: InnerColor::Fixed(color),
+            emission: 0,
+            fluid: None,
+            fluid_level: None,
+            snowy: false,
         }
     }
 }
 
 
+/**
+ *  vanilla light level of the light sources, by name and, for those that can be switched off, `lit`
+ */
+fn emission(state: &BlockStateKey) -> u8 {
+    let name = state.name.trim_start_matches("minecraft:");
+    match name {
+        "glowstone" | "sea_lantern" | "jack_o_lantern" | "beacon" | "lantern" | "shroomlight" | "conduit" 
+            | "end_gateway" | "end_portal" | "fire" | "lava" => 15,
+        "torch" | "wall_torch" | "end_rod" => 14,
+        "nether_portal" => 11,
+        "soul_fire" | "soul_lantern" | "soul_torch" | "soul_wall_torch" | "crying_obsidian" => 10,
+        "magma_block" => 3,
+        "brewing_stand" | "brown_mushroom" | "dragon_egg" | "end_portal_frame" => 1,
+        "redstone_lamp" | "campfire" if state.lit() => 15,
+        "furnace" | "smoker" | "blast_furnace" if state.lit() => 13,
+        "soul_campfire" if state.lit() => 10,
+        "redstone_ore" if state.lit() => 9,
+        "redstone_torch" | "redstone_wall_torch" if state.lit() => 7,
+        "sea_pickle" if state.waterlogged() => 3 * (state.number("pickles").unwrap_or(1) + 1),
+        _ => 0,
+    }
+}
+
+
+/**
+ *  key entry for blocks that can not be drawn: `pvd.placeholder()` tinted with `color`, or nothing
+ */
+pub fn placeholder_key(pvd: &ModelProvider, color: Option<[u8; 3]>) -> (Vec<Model>, BlockProps) {
+    match (color, pvd.placeholder()) {
+        (Some(color), Some(model)) => (vec![Weighted::from(model.clone())], BlockProps::placeholder(color)),
+        _ => (Vec::new(), BlockProps::new()),
+    }
+}
+
+
+/**
+ *  every distinct blockstate the tiles of a batch name, resolved once; tiles map their own key ids 
+ *  to ids here. id 0 is the placeholder of malformed key lines
+ */
+pub struct BlockRegistry<'a> {
+
+    pvd: &'a ModelProvider,
+
+    placeholder: Option<[u8; 3]>,
+
+    ids: HashMap<(String, Option<String>), usize>,
+
+    entries: Vec<RegistryEntry>,
+
+}
+
+struct RegistryEntry {
+    key: (Vec<Model>, BlockProps),
+    name: String,
+    problem: Option<KeyProblem>,
+}
+
+enum KeyProblem {
+    Unknown,
+    Unmatched(MatchError),
+}
+
+impl<'a> BlockRegistry<'a> {
+
+    /**
+     *  blocks that are unknown or fail to resolve are drawn as `pvd.placeholder()` tinted with `placeholder`; 
+     *  `None` leaves them empty
+     */
+    pub fn new(pvd: &'a ModelProvider, placeholder: Option<[u8; 3]>) -> Self {
+        BlockRegistry {
+            pvd,
+            placeholder,
+            ids: HashMap::new(),
+            entries: vec![RegistryEntry { key: placeholder_key(pvd, placeholder), name: String::new(), problem: None }],
+        }
+    }
+
+    pub fn pvd(&self) -> &'a ModelProvider {
+        self.pvd
+    }
+
+    /**
+     *  id of the blockstate of key line `k`, resolved the first time it is seen
+     */
+    pub fn intern(&mut self, k: &KeyLine) -> usize {
+        let entry = (k.name.to_string(), k.state.map(str::to_string));
+        if let Some(id) = self.ids.get(&entry) {
+            return *id;
+        }
+        let pvd = self.pvd;
+        let model = pvd.resolve(k.name, SplitIter::from(k.state));
+        let mut props = BlockProps::new_from(k.name, SplitIter::from(k.state));
+        let (key, problem) = match model {
+            _ if !props.air && !pvd.contains(k.name) => (placeholder_key(pvd, self.placeholder), Some(KeyProblem::Unknown)),
+            Err(error) => (placeholder_key(pvd, self.placeholder), Some(KeyProblem::Unmatched(error))),
+            Ok(model) if !props.air && model.is_empty() => (placeholder_key(pvd, self.placeholder), None),
+            Ok(model) => {
+                props.set_occlusion(&model);
+                ((model, props), None)
+            },
+        };
+        let id = self.entries.len();
+        self.entries.push(RegistryEntry { key, name: k.name.to_string(), problem });
+        self.ids.insert(entry, id);
+        id
+    }
+
+    /**
+     *  what is wrong with blockstate `id`, reported at key line `line` of a tile
+     */
+    pub fn issue(&self, id: usize, line: usize) -> Option<LoadError> {
+        let entry = &self.entries[id];
+        match &entry.problem {
+            Some(KeyProblem::Unknown) => Some(LoadError::UnknownBlock { line, name: entry.name.clone() }),
+            Some(KeyProblem::Unmatched(error)) => Some(LoadError::UnmatchedState { name: entry.name.clone(), error: error.clone() }),
+            None => None,
+        }
+    }
+
+    pub fn get(&self, id: usize) -> &(Vec<Model>, BlockProps) {
+        &self.entries[id].key
+    }
+
+    /**
+     *  how many distinct blockstates were seen, the placeholder included
+     */
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+
 pub struct Tile {
 
     id: (i32, i32),
 
+    format: TileFormat,
+
     data: Vec<u8>,
 
-    key: Vec<(Vec<Model>, BlockProps)>,
+    key: Vec<usize>,    // by key id - 1, ids in the `BlockRegistry` the tile was loaded with
+
+    issues: Vec<LoadError>,
 
 }
 
 impl Tile {
 
-    pub fn load<R: Read + Seek>(reader: R, id: (i32, i32), pvd: &ModelProvider) -> GEResult<Self> {
-        let mut zip = ZipArchive::new(reader).map_err(Box::new)?;
+    pub fn load<R: Read + Seek>(reader: R, id: (i32, i32), registry: &mut BlockRegistry) -> Result<Self, LoadError> {
+        let mut zip = ZipArchive::new(reader)?;
         let mut data = Vec::new();
-        let n = zip.by_name("data").map_err(Box::new)?.read_to_end(&mut data).map_err(Box::new)?;
-        if n != 256 * 256 * 18 {
-            return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "data")))
-        }
+        let n = zip.by_name("data").map_err(LoadError::entry("data"))?.read_to_end(&mut data)?;
+        let format = TileFormat::detect(n).ok_or(LoadError::SizeMismatch(n))?;
         let mut key = Vec::new();
+        let mut issues = Vec::new();
         let mut key_string = String::new();
-        let n = zip.by_name("key").map_err(Box::new)?.read_to_string(&mut key_string).map_err(Box::new)?;
-        for line in key_string.lines() {
+        zip.by_name("key").map_err(LoadError::entry("key"))?.read_to_string(&mut key_string)?;
+        for (i, line) in key_string.lines().enumerate() {
             match KeyLine::try_from(line) {
                 Ok(k) => {
-                    let model = pvd.get(k.name, SplitIter::from(k.state));
-                    let props = BlockProps::new_from(k.name, SplitIter::from(k.state));
-                    key.push((model, props));
+                    let global = registry.intern(&k);
+                    if let Some(issue) = registry.issue(global, i + 1) {
+                        issues.push(issue);
+                    }
+                    key.push(global);
                 },
                 Err(e) => {
-                    eprintln!("parse error: `{}` @{}", line, e); //TODO: log
-                    key.push((Vec::new(), BlockProps::new()))
+                    issues.push(e.at_line(i + 1));
+                    key.push(0)
+                }
+            }
+        }
+        // a truncated key: reported once, the blocks are drawn as the placeholder
+        let view = TileView { raw: data.as_slice(), format };
+        'scan: for z in 0 .. 256 {
+            for x in 0 .. 256 {
+                let element = view.element(x, z);
+                for layer in [element.shading(), element.seafloor(), element.ceil(), element.vegetation()].iter() {
+                    if layer.blockstate_id() as usize > key.len() {
+                        issues.push(LoadError::MissingKeyId(layer.blockstate_id()));
+                        break 'scan;
+                    }
                 }
             }
         }
         Ok(Tile {
             id,
+            format,
             data,
-            key
+            key,
+            issues,
         })
     }
 
+    /**
+     *  names of the blocks in the `key` of a tile, without loading its data; malformed lines are left out
+     */
+    pub fn scan_names<R: Read + Seek>(reader: R) -> Result<Vec<String>, LoadError> {
+        let mut zip = ZipArchive::new(reader)?;
+        let mut key_string = String::new();
+        zip.by_name("key").map_err(LoadError::entry("key"))?.read_to_string(&mut key_string)?;
+        Ok(key_string.lines().filter_map(|line| KeyLine::try_from(line).ok()).map(|k| k.name.to_string()).collect())
+    }
+
+    /**
+     *  non-fatal problems found while loading, e.g. malformed key lines
+     */
+    pub fn issues(&self) -> &[LoadError] {
+        self.issues.as_slice()
+    }
+
     pub fn view<'a>(&'a self) -> TileView<'a> {
-        TileView { raw: self.data.as_slice() }
+        TileView { raw: self.data.as_slice(), format: self.format }
+    }
+
+    pub fn format(&self) -> TileFormat {
+        self.format
     }
 
-    pub fn get_model(&self, id: u16) -> &(Vec<Model>, BlockProps) {
-        &self.key[(id - 1) as usize]
+    /**
+     *  the `BlockRegistry` id of key id `id`; the placeholder, 0, for ids the key does not have
+     */
+    pub fn global_id(&self, id: u16) -> usize {
+        self.key.get((id as usize).wrapping_sub(1)).copied().unwrap_or(0)
     }
 }
 
@@ -220,28 +637,29 @@ pub struct KeyLine<'a> {
 
 
 impl<'a> TryFrom<&'a str> for KeyLine<'a> {
-    type Error = usize;
+    type Error = LoadError;
 
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let err = |column| LoadError::KeyLine { line: 0, column };
         let mut state = None;
 
         let mut p = value;
         let mut pos = 0;
 
-        let i = p.find(' ').ok_or(pos)?;
-        let id = p[0..i].parse().map_err(|e| pos)?;
+        let i = p.find(' ').ok_or_else(|| err(pos))?;
+        let id = p[0..i].parse().map_err(|_| err(pos))?;
         p = &p[i+1..];
         pos += i + 1;
 
-        let i = p.find('{').ok_or(pos)?;
+        let i = p.find('{').ok_or_else(|| err(pos))?;
         let s = &p[0..i];
         if s != "Block" {
-            return Err(pos);
+            return Err(err(pos));
         }
         p = &p[i+1..];
         pos += i + 1;
 
-        let i = p.find('}').ok_or(pos)?;
+        let i = p.find('}').ok_or_else(|| err(pos))?;
         let name = &p[0..i];
         p = &p[i+1..];
         pos += i + 1;
@@ -249,7 +667,7 @@ impl<'a> TryFrom<&'a str> for KeyLine<'a> {
         if p.starts_with('[') {
             p = &p[1..];
             pos += 1;
-            let i = p.find(']').ok_or(pos)?;
+            let i = p.find(']').ok_or_else(|| err(pos))?;
             state = Some(&p[0..i]);
         }
 
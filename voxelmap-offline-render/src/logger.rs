@@ -0,0 +1,66 @@
+use std::io;
+use std::io::Write;
+use std::fs::File;
+use std::sync::Mutex;
+
+use log::Log;
+use log::Level;
+use log::LevelFilter;
+use log::Metadata;
+use log::Record;
+use log::SetLoggerError;
+
+
+pub struct Logger {
+    level: LevelFilter,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("[{:<5}] {}: {}\n", record.level(), record.target(), record.args());
+        match &self.file {
+            Some(file) => {
+                if let Ok(mut file) = file.lock() {
+                    let _ = file.write_all(line.as_bytes());
+                }
+            },
+            None => {
+                if record.level() <= Level::Warn {
+                    let _ = io::stderr().write_all(line.as_bytes());
+                } else {
+                    let _ = io::stdout().write_all(line.as_bytes());
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/**
+ *  install the global logger; records go to `file` if given, otherwise to stdout/stderr
+ */
+pub fn init(level: LevelFilter, file: Option<File>) -> Result<(), SetLoggerError> {
+    let logger = Logger {
+        level,
+        file: file.map(Mutex::new),
+    };
+    log::set_boxed_logger(Box::new(logger))?;
+    log::set_max_level(level);
+    Ok(())
+}
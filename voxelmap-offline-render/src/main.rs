@@ -1,7 +1,16 @@
 mod loader;
 mod framework;
+mod anvil;
+mod logger;
 
-use clap::{Arg, App, ArgSettings};
+use std::fs::File;
+use std::str::FromStr;
+
+use clap::{Arg, App, SubCommand, ArgSettings};
+use log::LevelFilter;
+
+use mc_render::model::model::DisplayContext;
+use mc_render::glrender::glow::Glow;
 
 fn main() {
     
@@ -15,6 +24,7 @@ fn main() {
                 .long("assets")
                 .takes_value(true)
                 .multiple(true)
+                .global(true)
                 .help("assets archive; for example: .minecraft/versions/1.15.1/1.15.1.jar")
         )
         .arg(
@@ -24,6 +34,23 @@ fn main() {
                 .takes_value(true)
                 .help("cache data folder")
         )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["voxelmap", "anvil"])
+                .default_value("voxelmap")
+                .help("input format: voxelmap cache (`x,z.zip`) or anvil region files (`r.x.z.mca`)")
+        )
+        .arg(
+            Arg::with_name("view")
+                .long("view")
+                .takes_value(true)
+                .possible_values(&["top-down", "isometric"])
+                .default_value("top-down")
+                .help("camera: straight down, or isometric from the south-east")
+        )
         .arg(
             Arg::with_name("output-folder")
                 .short("o")
@@ -38,22 +65,253 @@ fn main() {
                 .takes_value(true)
                 .help("image scale, from 1 to 4")
         )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .help("write missing-asset report (json) to file")
+        )
+        .arg(
+            Arg::with_name("blocks")
+                .long("blocks")
+                .takes_value(true)
+                .global(true)
+                .help("`reports/blocks.json` of the data generator; properties a key line leaves out get their default value")
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .requires("blocks")
+                .help("report blockstates with properties or values `--blocks` does not know instead of ignoring them")
+        )
+        .arg(
+            Arg::with_name("model-cache")
+                .long("model-cache")
+                .takes_value(true)
+                .global(true)
+                .help("keep built models and textures in file, rebuilt when the assets change")
+        )
+        .arg(
+            Arg::with_name("lazy")
+                .long("lazy")
+                .help("build only the blockstates the voxelmap `key` files refer to")
+        )
+        .arg(
+            Arg::with_name("placeholder-color")
+                .long("placeholder-color")
+                .takes_value(true)
+                .validator(|s| parse_color(&s).map(|_| ()))
+                .help("color of unknown blocks as RRGGBB, or `none` to leave them out")
+        )
+        .arg(
+            Arg::with_name("water-depth")
+                .long("water-depth")
+                .takes_value(true)
+                .validator(|s| parse_positive::<i32>(&s).map(|_| ()))
+                .help("darken water by its depth down to the seafloor; water this deep and deeper is darkest (voxelmap input)")
+        )
+        .arg(
+            Arg::with_name("water-curve")
+                .long("water-curve")
+                .takes_value(true)
+                .validator(|s| parse_positive::<f32>(&s).map(|_| ()))
+                .requires("water-depth")
+                .help("exponent of the depth fraction for `--water-depth`, below 1 darkens shallow water faster; default 0.5")
+        )
+        .arg(
+            Arg::with_name("night")
+                .long("night")
+                .help("light by the stored block and sky light instead of full daylight")
+        )
+        .arg(
+            Arg::with_name("glow")
+                .long("glow")
+                .takes_value(true)
+                .validator(|s| parse_positive::<u32>(&s).map(|_| ()))
+                .help("glow of this radius in pixels around light sources, best with `--night`")
+        )
+        .arg(
+            Arg::with_name("glow-strength")
+                .long("glow-strength")
+                .takes_value(true)
+                .validator(|s| parse_positive::<f32>(&s).map(|_| ()))
+                .requires("glow")
+                .help("how much of the glow is added, default 1.0")
+        )
+        .arg(
+            Arg::with_name("msaa")
+                .long("msaa")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&["0", "1", "2", "4", "8", "16"])
+                .help("multisample anti-aliasing with this many samples per pixel, for example 4")
+        )
+        .arg(
+            Arg::with_name("supersample")
+                .long("supersample")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&["1", "2", "3", "4"])
+                .help("render this many times as large and filter down to the output size, for example 2")
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .takes_value(true)
+                .possible_values(&["off", "error", "warn", "info", "debug", "trace"])
+                .default_value("info")
+                .help("log level")
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .takes_value(true)
+                .help("write log to file instead of stdout/stderr")
+        )
+        .subcommand(
+            SubCommand::with_name("icons")
+                .about("render an inventory icon of every blockstate")
+                .arg(
+                    Arg::with_name("output-folder")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .required(true)
+                        .help("output image folder")
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .short("n")
+                        .long("size")
+                        .takes_value(true)
+                        .default_value("64")
+                        .help("icon width and height in pixels")
+                )
+                .arg(
+                    Arg::with_name("display")
+                        .long("display")
+                        .takes_value(true)
+                        .possible_values(&["gui", "fixed", "ground", "head"])
+                        .default_value("gui")
+                        .help("model display transform: inventory slot, item frame, dropped item or worn on the head")
+                )
+        )
         .get_matches();
 
+    let level = matches.value_of("log-level").and_then(|s| s.parse().ok()).unwrap_or(LevelFilter::Info);
+    let log_file = match matches.value_of("log-file") {
+        Some(path) => match File::create(path) {
+            Ok(ofile) => Some(ofile),
+            Err(e) => {
+                eprintln!("log file [{}]: {}", path, e);
+                None
+            }
+        },
+        None => None,
+    };
+    logger::init(level, log_file).unwrap();
+
     let mut options = framework::AppOptions::default();
 
     if let Some(input) = matches.value_of("input-folder") {
         options.cache_folder = input.to_string();
     }
 
+    if matches.value_of("format") == Some("anvil") {
+        options.input_format = framework::InputFormat::Anvil;
+    }
+
+    if matches.value_of("view") == Some("isometric") {
+        options.view = framework::View::Isometric;
+    }
+
     if let Some(output) = matches.value_of("output-folder") {
         options.output_folder = output.to_string();
     }
 
+    if let Some(report) = matches.value_of("report") {
+        options.report_file = Some(report.to_string());
+    }
+
+    if let Some(blocks) = matches.value_of("blocks") {
+        options.blocks_report = Some(blocks.to_string());
+    }
+
+    options.strict = matches.is_present("strict");
+
+    if let Some(model_cache) = matches.value_of("model-cache") {
+        options.model_cache = Some(model_cache.to_string());
+    }
+
+    options.lazy = matches.is_present("lazy");
+
+    if let Some(color) = matches.value_of("placeholder-color") {
+        options.placeholder_color = parse_color(color).unwrap();
+    }
+
+    if let Some(max_depth) = matches.value_of("water-depth") {
+        let mut depth = loader::WaterDepth { max_depth: parse_positive(max_depth).unwrap(), .. Default::default() };
+        if let Some(curve) = matches.value_of("water-curve") {
+            depth.curve = parse_positive(curve).unwrap();
+        }
+        options.water_depth = Some(depth);
+    }
+
+    options.night_mod = matches.is_present("night");
+
+    if let Some(radius) = matches.value_of("glow") {
+        let mut glow = Glow { radius: parse_positive(radius).unwrap(), .. Default::default() };
+        if let Some(strength) = matches.value_of("glow-strength") {
+            glow.strength = parse_positive(strength).unwrap();
+        }
+        options.glow = Some(glow);
+    }
+
+    if let Some(samples) = matches.value_of("msaa").map(|s| s.parse().unwrap()) {
+        options.antialias.msaa = samples;
+    }
+
+    if let Some(k) = matches.value_of("supersample").map(|s| s.parse().unwrap()) {
+        options.antialias.supersample = k;
+    }
+
     if let Some(assets) = matches.values_of("assets") {
         options.assets = assets.map(|s| s.to_string()).collect();
     }
 
+    if let Some(icons) = matches.subcommand_matches("icons") {
+        if let Some(assets) = icons.values_of("assets") {
+            options.assets = assets.map(|s| s.to_string()).collect();
+        }
+        if let Some(blocks) = icons.value_of("blocks") {
+            options.blocks_report = Some(blocks.to_string());
+        }
+        if let Some(model_cache) = icons.value_of("model-cache") {
+            options.model_cache = Some(model_cache.to_string());
+        }
+        if let Some(samples) = icons.value_of("msaa").map(|s| s.parse().unwrap()) {
+            options.antialias.msaa = samples;
+        }
+        if let Some(k) = icons.value_of("supersample").map(|s| s.parse().unwrap()) {
+            options.antialias.supersample = k;
+        }
+        if let Some(output) = icons.value_of("output-folder") {
+            options.output_folder = output.to_string();
+        }
+        if let Some(size) = icons.value_of("size").and_then(|s| s.parse::<u32>().ok()) {
+            options.width = size;
+            options.height = size;
+        }
+        let context = match icons.value_of("display") {
+            Some("fixed") => DisplayContext::Fixed,
+            Some("ground") => DisplayContext::Ground,
+            Some("head") => DisplayContext::Head,
+            _ => DisplayContext::Gui,
+        };
+        framework::icons(options, context).unwrap();
+        return;
+    }
+
     if let Some(scale) = matches.value_of("scale") {
         if let Ok(scale) = scale.parse::<u32>() {
             if scale <= 16 {
@@ -67,3 +325,27 @@ fn main() {
 
 }
 
+
+/**
+ *  `RRGGBB`, with or without `#`, or `none`
+ */
+fn parse_color(s: &str) -> Result<Option<[u8; 3]>, String> {
+    if s == "none" {
+        return Ok(None);
+    }
+    let hex = s.trim_start_matches('#');
+    match u32::from_str_radix(hex, 16) {
+        Ok(c) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(Some([(c >> 16) as u8, (c >> 8) as u8, c as u8])),
+        _ => Err(format!("`{}` is not a color, expected RRGGBB or `none`", s)),
+    }
+}
+
+/**
+ *  a number above zero
+ */
+fn parse_positive<T: FromStr + PartialOrd + Default>(s: &str) -> Result<T, String> {
+    match s.parse::<T>() {
+        Ok(v) if v > T::default() => Ok(v),
+        _ => Err(format!("`{}` is not a number above zero", s)),
+    }
+}